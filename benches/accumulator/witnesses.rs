@@ -0,0 +1,44 @@
+/// See https://bheisler.github.io/criterion.rs/book/getting_started.html to add more benchmarks.
+#[macro_use]
+extern crate criterion;
+
+use accumulator::group::{ClassGroup, Rsa2048, UnknownOrderGroup};
+use accumulator::{Accumulator, Witness};
+use criterion::Criterion;
+
+fn bench_individual_witnesses<G: UnknownOrderGroup>(witness: &Witness<G, u32>, elems: &[u32]) {
+  witness.compute_individual_witnesses(elems);
+}
+
+fn bench_individual_witnesses_cached<G: UnknownOrderGroup>(
+  witness: &Witness<G, u32>,
+  elems: &[u32],
+) {
+  witness.compute_individual_witnesses_cached(elems);
+}
+
+macro_rules! benchmark_individual_witnesses {
+  ($group_type : ty, $n : expr, $criterion: ident) => {
+    let group_type_str = String::from(stringify!($group_type)).to_lowercase();
+    let elems = (0..$n).collect::<Vec<u32>>();
+    let elems_2 = elems.clone();
+    let witness = Witness(Accumulator::<$group_type, u32>::empty());
+    let witness_2 = witness.clone();
+    $criterion.bench_function(
+      format! {"{}_individual_witnesses", group_type_str}.as_str(),
+      move |b| b.iter(|| bench_individual_witnesses(&witness, &elems)),
+    );
+    $criterion.bench_function(
+      format! {"{}_individual_witnesses_cached", group_type_str}.as_str(),
+      move |b| b.iter(|| bench_individual_witnesses_cached(&witness_2, &elems_2)),
+    );
+  };
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+  benchmark_individual_witnesses! {Rsa2048, 64, c};
+  benchmark_individual_witnesses! {ClassGroup, 4, c};
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -23,6 +23,8 @@ fn criterion_benchmark(c: &mut Criterion) {
     .unwrap(),
   ));
   let right = left.clone();
+  let id = ClassGroup::id();
+  let op_id_right = left.clone();
 
   // Generator element.
   let base = ClassGroup::elem((
@@ -42,6 +44,12 @@ fn criterion_benchmark(c: &mut Criterion) {
   let exp = Integer::from_str("65315").unwrap();
   let g_inv = base.clone();
   let g_sq = ClassGroup::unknown_order_elem();
+  // A form with full-size coefficients, on which squaring spends most of its time in `exp`.
+  let g_large = ClassGroup::exp(
+    &g_sq,
+    &Integer::from_str("65315136833896061809557254466951").unwrap(),
+  );
+  let g_large_2 = g_large.clone();
 
   let aa = Integer::from_str("16").unwrap();
   let bb = Integer::from_str("105").unwrap();
@@ -64,6 +72,9 @@ fn criterion_benchmark(c: &mut Criterion) {
   c.bench_function("group_class_op", move |b| {
     b.iter(|| ClassGroup::op(&left, &right))
   });
+  c.bench_function("group_class_op_id", move |b| {
+    b.iter(|| ClassGroup::op(&id, &op_id_right))
+  });
   c.bench_function("group_class_exp", move |b| {
     b.iter(|| ClassGroup::exp(&base, &exp))
   });
@@ -85,6 +96,12 @@ fn criterion_benchmark(c: &mut Criterion) {
   c.bench_function("group_class_square", move |b| {
     b.iter_with_setup(|| g_sq.clone(), |g| ClassGroup::square(&g))
   });
+  c.bench_function("group_class_square_large", move |b| {
+    b.iter(|| ClassGroup::square(&g_large))
+  });
+  c.bench_function("group_class_square_nudupl_large", move |b| {
+    b.iter(|| ClassGroup::square_nudupl(&g_large_2))
+  });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -3,7 +3,7 @@
 extern crate criterion;
 
 use criterion::Criterion;
-use accumulator::group::{ElemFrom, Group, Rsa2048};
+use accumulator::group::{ElemFrom, Group, Rsa2048, UnknownOrderGroup};
 use rug::Integer;
 use std::str::FromStr;
 
@@ -62,6 +62,36 @@ fn bench_exp<G: Group + ElemFrom<u8>>() {
   );
 }
 
+// Plain left-to-right square-and-multiply over `rug`, the hand-rolled alternative to the GMP
+// `pow_mod` behind `Rsa2048::exp`.
+fn square_and_multiply(base: &Integer, n: &Integer, modulus: &Integer) -> Integer {
+  let mut res = Integer::from(1);
+  for i in (0..n.significant_bits()).rev() {
+    res = res.square() % modulus;
+    if n.get_bit(i) {
+      res = res * base % modulus;
+    }
+  }
+  res
+}
+
+fn bench_exp_unknown_order_elem<G: UnknownOrderGroup>(exp: &Integer) {
+  G::exp_unknown_order_elem(exp);
+}
+
+fn bench_exp_unknown_order_elem_generic<G: UnknownOrderGroup>(exp: &Integer) {
+  G::exp(&G::unknown_order_elem(), exp);
+}
+
+// Checks `a * b == b` for pairs whose first operand is the identity, as in a verification loop.
+fn bench_op_id_checks<G: Group>(pairs: &[(G::Elem, G::Elem)]) -> usize {
+  pairs.iter().filter(|(a, b)| G::op(a, b) == *b).count()
+}
+
+fn bench_op_ref_id_checks<G: Group>(pairs: &[(G::Elem, G::Elem)]) -> usize {
+  pairs.iter().filter(|(a, b)| *G::op_ref(a, b) == *b).count()
+}
+
 fn bench_inv<G: Group + ElemFrom<u8>>() {
   G::inv(&G::elem(2));
 }
@@ -69,7 +99,68 @@ fn bench_inv<G: Group + ElemFrom<u8>>() {
 fn criterion_benchmark(c: &mut Criterion) {
   c.bench_function("group_rsa_op", |b| b.iter(bench_op::<Rsa2048>));
   c.bench_function("group_rsa_op_large", |b| b.iter(bench_op_large::<Rsa2048>));
+  // `op` alone on full-size reduced elements, without the `elem` calls of `bench_op_large`.
+  let g = Rsa2048::unknown_order_elem();
+  let (y, z) = (
+    Rsa2048::exp(&g, &Integer::from(u64::MAX)),
+    Rsa2048::exp(&g, &Integer::from(u64::MAX - 1)),
+  );
+  c.bench_function("group_rsa_op_reduced", move |b| {
+    b.iter(|| Rsa2048::op(&y, &z))
+  });
+  // Operands are built outside the closure so that only the identity fast path is measured.
+  let id = Rsa2048::id();
+  let x = Rsa2048::elem(
+    Integer::from_str(
+      "106610920435831899020588753249099054915951032185883121197718271189872278955399",
+    )
+    .unwrap(),
+  );
+  c.bench_function("group_rsa_op_id", move |b| b.iter(|| Rsa2048::op(&id, &x)));
+  let pairs = (0..64u64)
+    .map(|i| {
+      (
+        Rsa2048::id(),
+        Rsa2048::exp(&Rsa2048::unknown_order_elem(), &Integer::from(i)),
+      )
+    })
+    .collect::<Vec<_>>();
+  let pairs_2 = pairs.clone();
+  c.bench_function("group_rsa_op_id_checks", move |b| {
+    b.iter(|| bench_op_id_checks::<Rsa2048>(&pairs))
+  });
+  c.bench_function("group_rsa_op_ref_id_checks", move |b| {
+    b.iter(|| bench_op_ref_id_checks::<Rsa2048>(&pairs_2))
+  });
   c.bench_function("group_rsa_exp", |b| b.iter(bench_exp::<Rsa2048>));
+  let exp = Integer::from_str(
+    "65315136833896061809557254466951240071191890612435768575001173256020447546800029221544380288\
+    474666886816442984548106882909827295319824031764930714696522619672276938781971873901815262421",
+  )
+  .unwrap();
+  let exp_2 = exp.clone();
+  c.bench_function("group_rsa_exp_unknown_order_elem", move |b| {
+    b.iter(|| bench_exp_unknown_order_elem::<Rsa2048>(&exp))
+  });
+  c.bench_function("group_rsa_exp_unknown_order_elem_generic", move |b| {
+    b.iter(|| bench_exp_unknown_order_elem_generic::<Rsa2048>(&exp_2))
+  });
+  // 2048-bit exponent, for comparing `exp` against a hand-rolled square-and-multiply.
+  let base = Rsa2048::exp(&Rsa2048::unknown_order_elem(), &Integer::from(3));
+  let exp_2048: Integer = Integer::from(Integer::u_pow_u(3, 1292)) | 1;
+  let (base_2, exp_2048_2) = (base.clone(), exp_2048.clone());
+  c.bench_function("group_rsa_exp_2048", move |b| {
+    b.iter(|| Rsa2048::exp(&base, &exp_2048))
+  });
+  c.bench_function("group_rsa_exp_2048_square_and_multiply", move |b| {
+    b.iter(|| {
+      Rsa2048::elem(square_and_multiply(
+        &base_2.0,
+        &exp_2048_2,
+        Rsa2048::modulus(),
+      ))
+    })
+  });
   c.bench_function("group_rsa_inv", |b| b.iter(bench_inv::<Rsa2048>));
 }
 
@@ -10,13 +10,24 @@ use accumulator::util::int;
 fn bench_poe_rsa() {
   let base = Rsa2048::unknown_order_elem();
   let exp = int(20);
-  let result = Rsa2048::elem(1_048_576);
+  let result = Rsa2048::elem(1_099_511_627_776u64);
   let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
   Poe::<Rsa2048>::verify(&base, &exp, &result, &proof);
 }
 
+// Re-derives the challenge for one statement; much faster with the `challenge-cache` feature.
+fn bench_poe_challenge_repeated() {
+  let base = Rsa2048::unknown_order_elem();
+  let exp = int(20);
+  let result = Rsa2048::elem(1_099_511_627_776u64);
+  Poe::<Rsa2048>::challenge(&base, &exp, &result);
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
   c.bench_function("poe_rsa", |b| b.iter(bench_poe_rsa));
+  c.bench_function("poe_challenge_repeated", |b| {
+    b.iter(bench_poe_challenge_repeated)
+  });
 }
 
 criterion_group!(benches, criterion_benchmark);
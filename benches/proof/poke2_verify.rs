@@ -0,0 +1,50 @@
+/// Measures the group operations of `Poke2::verify` alone, with the Fiat-Shamir challenges computed
+/// up front, next to the full verification and the original two-sided check with four separate
+/// exponentiations for comparison.
+#[macro_use]
+extern crate criterion;
+
+use accumulator::group::{ClassGroup, Rsa2048, UnknownOrderGroup};
+use accumulator::proof::Poke2;
+use accumulator::util::int;
+use criterion::Criterion;
+
+fn bench_verify<G: UnknownOrderGroup>(c: &mut Criterion, name: &str) {
+  let base = G::unknown_order_elem();
+  let exp = int(1) << 2048;
+  let result = G::exp(&base, &exp);
+  let proof = Poke2::<G>::prove(&base, &exp, &result);
+  let (l, alpha) = Poke2::<G>::challenges(&base, &result, proof.z());
+  assert!(Poke2::verify_with_challenges(&base, &result, &proof, &l, &alpha).is_ok());
+
+  let (base_, result_, proof_) = (base.clone(), result.clone(), proof.clone());
+  let (l_, alpha_) = (l.clone(), alpha.clone());
+  c.bench_function(&format!("poke2_verify_group_ops_{}", name), move |b| {
+    b.iter(|| Poke2::verify_with_challenges(&base_, &result_, &proof_, &l_, &alpha_))
+  });
+  let (base_, result_, proof_) = (base.clone(), result.clone(), proof.clone());
+  c.bench_function(
+    &format!("poke2_verify_group_ops_two_sided_{}", name),
+    move |b| {
+      b.iter(|| {
+        let g = G::unknown_order_elem();
+        let lhs = G::op(
+          &G::exp(proof_.q(), &l),
+          &G::exp(&G::op(&base_, &G::exp(&g, &alpha)), proof_.r()),
+        );
+        lhs == G::op(&result_, &G::exp(proof_.z(), &alpha))
+      })
+    },
+  );
+  c.bench_function(&format!("poke2_verify_{}", name), move |b| {
+    b.iter(|| Poke2::verify(&base, &result, &proof))
+  });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+  bench_verify::<Rsa2048>(c, "rsa");
+  bench_verify::<ClassGroup>(c, "class");
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
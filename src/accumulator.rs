@@ -1,12 +1,84 @@
 //! Accumulator library, built on a generic group interface.
-use crate::group::UnknownOrderGroup;
-use crate::hash::hash_to_prime;
-use crate::proof::{Poe, Poke2};
-use crate::util::{divide_and_conquer, int, prime_hash_product, shamir_trick};
+//!
+//! Most proofs here are PoEs, whose exponent is part of the statement and so is revealed to the
+//! verifier: the prime hash product of the proven elements for membership proofs, of the added or
+//! deleted elements for `verify_deletion`, `prove_subset` and `prove_transition`, and of the
+//! initial elements for `genesis`. This is inherent for membership, since the verifier needs the
+//! element. Where the exponent should stay secret, a PoKE2 proves knowledge of it instead:
+//! `prove_membership_blinded` hides the witness, non-membership proofs hide the Bezout coefficient
+//! `b`, and `prove_subset_hidden` hides the extra elements of a superset.
+use crate::frozen_accumulator::FrozenAccumulator;
+use crate::group::{exp_pair, UnknownOrderGroup};
+#[cfg(feature = "nonmembership-cache")]
+use crate::hash::hash;
+use crate::hash::{hash_to_prime, Blake2b, GeneralHasher};
+use crate::proof::{Poe, Poke2, ProofError, SecurityLevel, Transcript};
+use crate::util::{bezout, divide_and_conquer, int, prime_hash_product, random_bits, shamir_trick};
+use rand_core::RngCore;
 use rug::Integer;
-use std::hash::Hash;
+#[cfg(feature = "nonmembership-cache")]
+use std::cell::RefCell;
+#[cfg(feature = "nonmembership-cache")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::marker::PhantomData;
 
+/// Domain separation tag prepended to the accumulator value when computing `digest`.
+const DIGEST_DOMAIN_TAG: &[u8] = b"accumulator/digest/v1";
+
+/// Domain label of the Fiat-Shamir transcript shared by the proofs in a `NonmembershipProof`.
+const NONMEMBERSHIP_DOMAIN: &[u8] = b"accumulator/nonmembership/v1";
+
+/// Maximum number of verification results memoized per thread under the `nonmembership-cache`
+/// feature. Once it fills up, the least recently used result is evicted.
+#[cfg(feature = "nonmembership-cache")]
+const NONMEMBERSHIP_CACHE_CAPACITY: usize = 1024;
+
+/// Memoized `verify_nonmembership` results, keyed by the `digest` of the queried accumulator and
+/// the Blake2b hash of the group descriptor, queried primes and proof. Each result is stored with
+/// the tick of the clock at which it was last used.
+#[cfg(feature = "nonmembership-cache")]
+#[derive(Default)]
+struct NonmembershipCache {
+  results: HashMap<([u8; 32], [u8; 32]), (bool, u64)>,
+  clock: u64,
+}
+
+#[cfg(feature = "nonmembership-cache")]
+impl NonmembershipCache {
+  fn get(&mut self, key: &([u8; 32], [u8; 32])) -> Option<bool> {
+    self.clock += 1;
+    let clock = self.clock;
+    self.results.get_mut(key).map(|(result, used)| {
+      *used = clock;
+      *result
+    })
+  }
+
+  fn insert(&mut self, key: ([u8; 32], [u8; 32]), result: bool) {
+    if self.results.len() >= NONMEMBERSHIP_CACHE_CAPACITY && !self.results.contains_key(&key) {
+      let lru = self
+        .results
+        .iter()
+        .min_by_key(|(_, (_, used))| *used)
+        .map(|(key, _)| *key);
+      if let Some(lru) = lru {
+        self.results.remove(&lru);
+      }
+    }
+    self.clock += 1;
+    self.results.insert(key, (result, self.clock));
+  }
+}
+
+#[cfg(feature = "nonmembership-cache")]
+thread_local! {
+  static NONMEMBERSHIP_CACHE: RefCell<NonmembershipCache> = RefCell::default();
+}
+
 #[derive(Debug)]
 /// The different types of accumulator errors.
 pub enum AccError {
@@ -24,6 +96,20 @@ pub enum AccError {
 
   /// Inputs not coprime when they were expected to be coprime.
   InputsNotCoprime,
+
+  /// No recorded state to undo to.
+  HistoryExhausted,
+
+  /// No elements were given where at least one was expected. The empty product `1` divides every
+  /// set product, so e.g. non-membership of no elements cannot be proven.
+  EmptyElems,
+
+  /// An exponent exceeded the configured bit-length cap, e.g. from
+  /// `TrackedAccumulator::with_max_exponent_bits`.
+  ExponentTooLarge,
+
+  /// An element to be deleted is not in the tracked set, e.g. in `TrackedAccumulator::delete`.
+  NotPresent,
 }
 
 // See https://doc.rust-lang.org/std/marker/struct.PhantomData.html#ownership-and-the-drop-check
@@ -34,6 +120,7 @@ pub enum AccError {
 /// representing the type `T` being hashed-to-prime and accumulated.
 pub struct Accumulator<G: UnknownOrderGroup, T> {
   phantom: PhantomData<T>,
+  /// The underlying group element.
   pub value: G::Elem,
 }
 
@@ -57,7 +144,15 @@ pub struct Witness<G: UnknownOrderGroup, T: Hash>(pub Accumulator<G, T>);
 pub struct MembershipProof<G: UnknownOrderGroup, T: Hash> {
   /// The witness for the element in question.
   pub witness: Witness<G, T>,
-  proof: Poe<G>,
+  pub(crate) proof: Poe<G>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A membership proof consisting of the witness alone, one group element smaller than a
+/// `MembershipProof`. See `MembershipProof::compact`.
+pub struct CompactMembershipProof<G: UnknownOrderGroup, T: Hash> {
+  /// The witness for the element in question.
+  pub witness: Witness<G, T>,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -71,6 +166,47 @@ pub struct NonmembershipProof<G: UnknownOrderGroup, T> {
   poe_proof: Poe<G>,
 }
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A proof that an accumulator state is obtained from another by removing some elements and then
+/// adding others. See `Accumulator::prove_transition`.
+pub struct TransitionProof<G: UnknownOrderGroup, T: Hash> {
+  intermediate: Accumulator<G, T>,
+  removal_proof: Poe<G>,
+  addition_proof: Poe<G>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A zero-knowledge variant of `MembershipProof` that hides the witness. See
+/// `Accumulator::prove_membership_blinded`.
+pub struct BlindedMembershipProof<G: UnknownOrderGroup, T> {
+  phantom: PhantomData<*const T>,
+  blinded_witness: G::Elem,
+  proof: Poke2<G>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A single update to an accumulator, as recorded in a log replayed by `Accumulator::replay`.
+pub enum AccOp<G: UnknownOrderGroup, T: Hash> {
+  /// Adds the elements, as by `Accumulator::add`.
+  Add(Vec<T>),
+
+  /// Removes the elements, each with its witness w.r.t. the accumulator the operation is applied
+  /// to, as by `Accumulator::delete`.
+  Delete(Vec<(T, Witness<G, T>)>),
+}
+
+/// Returns an independent accumulator base derived from `seed` via `G::generator`, for applications
+/// that keep several accumulators in the same group.
+///
+/// Accumulators sharing a base are not independent: two of them holding the same elements have
+/// the same value, so a membership proof for one verifies against the other, and in general their
+/// values differ by a known power of the base. Bases from distinct seeds have no known discrete-log
+/// relation to each other or to `G::unknown_order_elem()`, so proofs stay bound to the accumulator
+/// they were made for. Start an accumulator from the result with `Accumulator::empty_with_base`.
+pub fn setup_with_base<G: UnknownOrderGroup>(seed: &[u8]) -> G::Elem {
+  G::generator(seed)
+}
+
 impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
   /// Create an accumulator from a given one
   pub fn new_from(new: G::Elem) -> Self {
@@ -88,6 +224,42 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
     }
   }
 
+  /// Returns a new, empty accumulator over the base `setup_with_base(seed)`.
+  ///
+  /// Additions, deletions and membership proofs work as for `empty`. Nonmembership proofs,
+  /// `genesis` and `FromIterator` are defined relative to `G::unknown_order_elem()` and do not
+  /// apply to such an accumulator.
+  pub fn empty_with_base(seed: &[u8]) -> Self {
+    Self::new_from(setup_with_base::<G>(seed))
+  }
+
+  /// Returns the accumulator of `initial_elems` together with a PoE that it is the empty
+  /// accumulator raised to their prime hash product, so that a published genesis state can be
+  /// checked with `verify_genesis`.
+  pub fn genesis(initial_elems: &[T]) -> (Self, Poe<G>) {
+    let (acc, x) = Self::empty().add_(initial_elems);
+    let proof = Poe::prove(&G::unknown_order_elem(), &x, &acc.value);
+    (acc, proof)
+  }
+
+  /// Verifies a proof from `genesis` that this accumulator is built from `initial_elems` alone.
+  pub fn verify_genesis(&self, initial_elems: &[T], proof: &Poe<G>) -> bool {
+    let x = prime_hash_product(initial_elems);
+    Poe::verify(&G::unknown_order_elem(), &x, &self.value, proof)
+  }
+
+  /// Returns a canonical 256-bit fingerprint of the accumulator state: the Blake2b hash of a domain
+  /// tag followed by `G::to_bytes` of the accumulator value.
+  ///
+  /// Unlike the `Hash` impl, the digest does not depend on the in-memory representation of the
+  /// group element, so it can be compared against accumulators computed by other implementations.
+  pub fn digest(&self) -> [u8; 32] {
+    let mut hasher = Blake2b::default();
+    hasher.write(DIGEST_DOMAIN_TAG);
+    hasher.write(&G::to_bytes(&self.value));
+    hasher.finalize()
+  }
+
   /// Internal add method that also returns the prime hash product of added elements, enabling an
   /// efficient `add_with_proof`.
   fn add_(&self, elems: &[T]) -> (Self, Integer) {
@@ -126,6 +298,129 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
     )
   }
 
+  /// Like `add_with_proof`, but binds `context` into the proof, e.g. the height and timestamp of
+  /// the block containing the update, so that it only verifies via
+  /// `verify_membership_batch_in_context` under the same context. See `Poe::prove_with_context`.
+  pub fn add_with_proof_in_context(
+    self,
+    elems: &[T],
+    context: &[u8],
+  ) -> (Self, MembershipProof<G, T>) {
+    let (acc, x) = self.add_(elems);
+    let proof = Poe::<G>::prove_with_context(&self.value, &x, &acc.value, context);
+    (
+      acc,
+      MembershipProof {
+        witness: Witness(self),
+        proof,
+      },
+    )
+  }
+
+  /// Applies a net state change in one call: first removes the elements in `removals` (whose
+  /// witnesses are checked against the current accumulator, as in `delete`), then adds the elements
+  /// in `additions`. Returns the new accumulator along with a batch membership proof for the
+  /// removed elements w.r.t. the current accumulator and one for the added elements w.r.t. the new
+  /// one.
+  ///
+  /// Uses a move instead of a `&self` reference to prevent accidental use of the old accumulator.
+  #[allow(clippy::type_complexity)]
+  pub fn apply_delta(
+    self,
+    additions: &[T],
+    removals: &[(T, Witness<G, T>)],
+  ) -> Result<(Self, MembershipProof<G, T>, MembershipProof<G, T>), AccError> {
+    let (acc, removal_proof) = self.delete_with_proof(removals)?;
+    let (acc, addition_proof) = acc.add_with_proof(additions);
+    Ok((acc, removal_proof, addition_proof))
+  }
+
+  /// Applies the operations in `ops` in order, starting from this accumulator, e.g. so that a
+  /// syncing client can recompute the current accumulator from genesis and an operation log.
+  /// Returns `BadWitness` if some witness of a `Delete` is invalid for the accumulator at that
+  /// point.
+  ///
+  /// Uses a move instead of a `&self` reference to prevent accidental use of the old accumulator.
+  pub fn replay(self, ops: &[AccOp<G, T>]) -> Result<Self, AccError> {
+    ops.iter().try_fold(self, |acc, op| match op {
+      AccOp::Add(elems) => Ok(acc.add(elems)),
+      AccOp::Delete(elem_witnesses) => acc.delete(elem_witnesses),
+    })
+  }
+
+  /// Proves that `new_acc` results from this accumulator by removing the elements in `removals`
+  /// (whose witnesses are checked as in `delete`) and then adding the elements in `additions`. The
+  /// proof holds the intermediate accumulator together with a PoE for each step.
+  ///
+  /// Returns `BadWitness` if a witness is invalid or the transition does not lead to `new_acc`.
+  pub fn prove_transition(
+    &self,
+    new_acc: &Self,
+    additions: &[T],
+    removals: &[(T, Witness<G, T>)],
+  ) -> Result<TransitionProof<G, T>, AccError> {
+    let (acc, removal, addition) = self.clone().apply_delta(additions, removals)?;
+    if acc != *new_acc {
+      return Err(AccError::BadWitness);
+    }
+    Ok(TransitionProof {
+      intermediate: removal.witness.0,
+      removal_proof: removal.proof,
+      addition_proof: addition.proof,
+    })
+  }
+
+  /// Verifies a proof that `new_acc` results from this accumulator by removing `removals` and then
+  /// adding `additions`.
+  pub fn verify_transition(
+    &self,
+    new_acc: &Self,
+    additions: &[T],
+    removals: &[T],
+    TransitionProof {
+      intermediate,
+      removal_proof,
+      addition_proof,
+    }: &TransitionProof<G, T>,
+  ) -> bool {
+    Poe::verify(
+      &intermediate.value,
+      &prime_hash_product(removals),
+      &self.value,
+      removal_proof,
+    ) && Poe::verify(
+      &intermediate.value,
+      &prime_hash_product(additions),
+      &new_acc.value,
+      addition_proof,
+    )
+  }
+
+  /// Adds `elems` to the accumulator one at a time, returning the accumulator after each element
+  /// is folded in along with a membership proof for that element w.r.t. the new accumulator (whose
+  /// witness is the previous accumulator).
+  ///
+  /// This is more expensive than a batched `add_with_proof`, since it computes `n` small proofs
+  /// instead of one. Use it only when the intermediate states are needed, e.g. for audit logs.
+  pub fn add_stepwise(self, elems: &[T]) -> Vec<(Self, MembershipProof<G, T>)> {
+    let mut steps = Vec::with_capacity(elems.len());
+    let mut acc = self;
+    for elem in elems {
+      let p = hash_to_prime(elem);
+      let next = Self::new_from(G::exp(&acc.value, &p));
+      let proof = Poe::<G>::prove(&acc.value, &p, &next.value);
+      steps.push((
+        next.clone(),
+        MembershipProof {
+          witness: Witness(acc),
+          proof,
+        },
+      ));
+      acc = next;
+    }
+    steps
+  }
+
   /// Internal delete method that also returns the prime hash product of deleted elements, enabling
   /// an efficient `delete_with_proof`.
   ///
@@ -138,24 +433,33 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
       .collect::<Vec<_>>();
 
     for (p, witness_elem) in &prime_witnesses {
-      if G::exp(&witness_elem, &p) != self.value {
+      if G::exp(witness_elem, p) != self.value {
         return Err(AccError::BadWitness);
       }
     }
 
+    Ok(self.delete_primes_(&prime_witnesses))
+  }
+
+  /// Removes the primes in `prime_witnesses` from the accumulator, assuming each witness has been
+  /// checked. Returns the new accumulator and the product of the removed primes.
+  fn delete_primes_(self, prime_witnesses: &[(Integer, G::Elem)]) -> (Self, Integer) {
     let (prime_product, acc_elem) = divide_and_conquer(
-      |(p1, v1), (p2, v2)| Ok((int(p1 * p2), shamir_trick::<G>(&v1, &v2, p1, p2).unwrap())),
+      |(p1, v1), (p2, v2)| -> Result<_, AccError> {
+        Ok((int(p1 * p2), shamir_trick::<G>(v1, v2, p1, p2).unwrap()))
+      },
       (int(1), self.value),
-      &prime_witnesses[..],
-    )?;
+      prime_witnesses,
+    )
+    .unwrap();
 
-    Ok((
+    (
       Self {
         phantom: PhantomData,
-        value: acc_elem.clone(),
+        value: acc_elem,
       },
       prime_product,
-    ))
+    )
   }
 
   /// Removes the elements in `elem_witnesses` from the accumulator.
@@ -171,6 +475,10 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
 
   /// A specialized version of `delete` that also returns a batch membership proof for deleted
   /// elements.
+  ///
+  /// The proof is a deterministic function of the old accumulator, the new one and the product of
+  /// the deleted primes, none of which depend on the order of `elem_witnesses`, so reordering the
+  /// input yields byte-identical proofs.
   pub fn delete_with_proof(
     self,
     elem_witnesses: &[(T, Witness<G, T>)],
@@ -186,6 +494,127 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
     ))
   }
 
+  /// Verifies that `new_acc` is this accumulator with elements whose prime hash product is
+  /// `deleted_product` removed, given the PoE (see `MembershipProof::poe`) returned by
+  /// `delete_with_proof`. Neither the deleted elements nor their witnesses are needed.
+  pub fn verify_deletion(&self, new_acc: &Self, deleted_product: &Integer, proof: &Poe<G>) -> bool {
+    Poe::verify(&new_acc.value, deleted_product, &self.value, proof)
+  }
+
+  /// A best-effort version of `delete_with_proof` that skips elements whose witnesses are invalid
+  /// (e.g. because the element is not in the accumulator) instead of failing. Returns the new
+  /// accumulator, a batch membership proof for the elements that were actually deleted, and the
+  /// indices into `elem_witnesses` of the skipped elements. Repeats of an element already deleted
+  /// earlier in `elem_witnesses` are skipped too, since it can only be removed once.
+  ///
+  /// Use `delete` or `delete_with_proof` if an invalid witness should be treated as an error.
+  pub fn delete_present(
+    self,
+    elem_witnesses: &[(T, Witness<G, T>)],
+  ) -> (Self, MembershipProof<G, T>, Vec<usize>) {
+    let mut skipped = Vec::new();
+    let mut deleted = HashSet::new();
+    let mut prime_witnesses = Vec::with_capacity(elem_witnesses.len());
+    for (i, (elem, witness)) in elem_witnesses.iter().enumerate() {
+      let p = hash_to_prime(elem);
+      if !deleted.contains(&p) && G::exp(&witness.0.value, &p) == self.value {
+        deleted.insert(p.clone());
+        prime_witnesses.push((p, witness.0.value.clone()));
+      } else {
+        skipped.push(i);
+      }
+    }
+
+    let (acc, prime_product) = self.clone().delete_primes_(&prime_witnesses);
+    let proof = Poe::<G>::prove(&acc.value, &prime_product, &self.value);
+    (
+      acc.clone(),
+      MembershipProof {
+        witness: Witness(acc),
+        proof,
+      },
+      skipped,
+    )
+  }
+
+  /// Internal method behind `delete_from_product` that also returns the old accumulator, which is
+  /// computed anyway to produce the proof.
+  fn delete_from_product_(
+    base: &G::Elem,
+    full_product: &Integer,
+    elems: &[T],
+  ) -> Result<(Self, Self, MembershipProof<G, T>), AccError> {
+    let mut remaining = full_product.clone();
+    let mut deleted = int(1);
+    for elem in elems {
+      let p = hash_to_prime(elem);
+      if !remaining.is_divisible(&p) {
+        return Err(AccError::BadWitness);
+      }
+      remaining.div_exact_mut(&p);
+      deleted *= p;
+    }
+
+    let acc = Self::new_from(G::exp(base, &remaining));
+    let old_acc = Self::new_from(G::exp(&acc.value, &deleted));
+    let proof = Poe::<G>::prove(&acc.value, &deleted, &old_acc.value);
+    Ok((
+      acc.clone(),
+      old_acc,
+      MembershipProof {
+        witness: Witness(acc),
+        proof,
+      },
+    ))
+  }
+
+  /// Removes `elems` from the accumulator `base ^ full_product` without per-element witnesses, by
+  /// dividing their prime hashes out of `full_product` and recomputing from `base`. Also returns a
+  /// batch membership proof for the deleted elements w.r.t. the old accumulator.
+  ///
+  /// Intended for servers that store the full accumulated product rather than witnesses. Returns
+  /// `BadWitness` if the hash of some element in `elems` does not divide the remaining product.
+  pub fn delete_from_product(
+    base: &G::Elem,
+    full_product: &Integer,
+    elems: &[T],
+  ) -> Result<(Self, MembershipProof<G, T>), AccError> {
+    let (acc, _, proof) = Self::delete_from_product_(base, full_product, elems)?;
+    Ok((acc, proof))
+  }
+
+  /// Computes the accumulator `base ^ full_product` together with a membership proof for `elem`
+  /// w.r.t. it, whose witness is `base ^ (full_product / elem)`. Returns `BadWitness` if the hash
+  /// of `elem` does not divide `full_product`.
+  ///
+  /// Cheaper than computing the accumulator and the proof separately, since the accumulator is
+  /// derived from the witness.
+  pub fn prove_membership_from_product(
+    base: &G::Elem,
+    full_product: &Integer,
+    elem: &T,
+  ) -> Result<(Self, MembershipProof<G, T>), AccError> {
+    let (_, acc, proof) =
+      Self::delete_from_product_(base, full_product, std::slice::from_ref(elem))?;
+    Ok((acc, proof))
+  }
+
+  /// Freezes the accumulator into a read-only `FrozenAccumulator` that caches the witness of each
+  /// element of `elems`, computed from `elems_witness`, a witness for all of them together. For an
+  /// accumulator built by adding `elems` to `empty()`, that is `Witness(Accumulator::empty())`.
+  ///
+  /// Returns `BadWitness` if `elems_witness` is not a witness for `elems`.
+  pub fn freeze(
+    self,
+    elems_witness: &Witness<G, T>,
+    elems: &[T],
+  ) -> Result<FrozenAccumulator<G, T>, AccError>
+  where
+    T: Clone,
+  {
+    FrozenAccumulator::new(self, elems_witness, elems)
+  }
+
   /// Computes the batch membership proof for the elements in `elem_witnesses` w.r.t this
   /// accumulator.
   ///
@@ -208,8 +637,54 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
     })
   }
 
+  /// Aggregates the membership witnesses of several primes into one proof: the witnesses are
+  /// Shamir-combined into a single witness for the product `x` of the primes, and a single PoE
+  /// shows that it raised to `x` is the accumulator. The proof is as small as one for a single
+  /// element. Returns `x` together with the proof, which verifies with `verify_membership_prime`
+  /// on `x`, or with `verify_membership_batch` on the elements hashing to the primes.
+  ///
+  /// Returns `BadWitness` if some `witness ^ prime` is not the accumulator, `InputsNotCoprime` if a
+  /// prime is repeated, and `EmptyElems` if `prime_witnesses` is empty.
+  pub fn prove_membership_aggregate(
+    &self,
+    prime_witnesses: &[(&Integer, &G::Elem)],
+  ) -> Result<(Integer, MembershipProof<G, T>), AccError> {
+    if prime_witnesses.is_empty() {
+      return Err(AccError::EmptyElems);
+    }
+    let prime_witnesses = prime_witnesses
+      .iter()
+      .map(|(p, witness_elem)| ((*p).clone(), (*witness_elem).clone()))
+      .collect::<Vec<_>>();
+    for (p, witness_elem) in &prime_witnesses {
+      if G::exp(witness_elem, p) != self.value {
+        return Err(AccError::BadWitness);
+      }
+    }
+    let (product, witness_elem) = divide_and_conquer(
+      |(p1, v1), (p2, v2)| -> Result<_, AccError> {
+        let v = shamir_trick::<G>(v1, v2, p1, p2).ok_or(AccError::InputsNotCoprime)?;
+        Ok((int(p1 * p2), v))
+      },
+      (int(1), self.value.clone()),
+      &prime_witnesses,
+    )?;
+    let proof = Poe::<G>::prove(&witness_elem, &product, &self.value);
+    Ok((
+      product,
+      MembershipProof {
+        witness: Witness(Self::new_from(witness_elem)),
+        proof,
+      },
+    ))
+  }
+
   /// Verifies a membership proof against the current accumulator and an element `t` whose
   /// inclusion is being proven.
+  ///
+  /// The proof only verifies for the exact element it was computed for: the PoE challenge is
+  /// derived from the prime hash of `t`, so another element whose prime agrees with it modulo the
+  /// challenge is still rejected.
   pub fn verify_membership(
     &self,
     t: &T,
@@ -219,14 +694,169 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
     Poe::verify(&witness.0.value, &exp, &self.value, proof)
   }
 
-  /// Batch version of `verify_membership` for multiple `elems`.
+  /// Decodes a membership proof for `t` from the encoding produced by `MembershipProof::to_bytes`
+  /// and verifies it, in one call for callers that receive proofs over the wire. Returns the
+  /// decoding error if `bundle` is malformed.
+  pub fn verify_membership_bundle(&self, t: &T, bundle: &[u8]) -> Result<bool, ProofError> {
+    let proof = MembershipProof::try_from(bundle)?;
+    Ok(self.verify_membership(t, &proof))
+  }
+
+  /// Verifies a compact membership proof for `t` by checking `witness ^ x = acc` directly, where
+  /// `x` is the prime hash of `t`.
+  pub fn verify_membership_compact(&self, t: &T, proof: &CompactMembershipProof<G, T>) -> bool {
+    G::exp(&proof.witness.0.value, &hash_to_prime(t)) == self.value
+  }
+
+  /// Batch version of `verify_membership` for multiple `elems`. As with `verify_membership`, the
+  /// proof only verifies for a set of elements with exactly the prime hash product it was computed
+  /// for, i.e. for the same elements in any order.
   pub fn verify_membership_batch(
     &self,
     elems: &[T],
     MembershipProof { witness, proof }: &MembershipProof<G, T>,
+  ) -> bool {
+    let primes = elems.iter().map(hash_to_prime).collect::<Vec<_>>();
+    Poe::verify_product(&witness.0.value, &primes, &self.value, proof)
+  }
+
+  /// Like `verify_membership_batch`, for a proof from `add_with_proof_in_context` made under
+  /// `context`.
+  pub fn verify_membership_batch_in_context(
+    &self,
+    elems: &[T],
+    MembershipProof { witness, proof }: &MembershipProof<G, T>,
+    context: &[u8],
   ) -> bool {
     let exp = prime_hash_product(elems);
-    Poe::verify(&witness.0.value, &exp, &self.value, proof)
+    Poe::verify_with_context(&witness.0.value, &exp, &self.value, proof, context)
+  }
+
+  /// Computes a membership proof for the elements in `elem_witnesses` that does not reveal their
+  /// witness `w`. Instead, the proof contains the blinded witness `w' = w * acc ^ r` for a random
+  /// `r`, together with a PoKE2 that the prover knows `r` s.t. `(acc ^ x) ^ r = w' ^ x / acc`,
+  /// where `x` is the prime hash product of the elements.
+  ///
+  /// Soundness matches that of PoKE2: a prover that knows such an `r` knows the witness
+  /// `w' / acc ^ r`. Zero-knowledge is only computational, and holds for verifiers that cannot
+  /// compute `acc ^ r` from the `g ^ r` committed in the PoKE2, i.e. that do not know the product
+  /// of the accumulated set (a verifier that does could compute `w` directly anyway). In exchange,
+  /// the proof costs several more exponentiations to create and verify than `prove_membership`.
+  ///
+  /// `r` is sampled from `rng` with 128 bits more than the encoding of a group element, which is
+  /// enough to statistically hide `w` in the groups in this crate. `rng` must be cryptographically
+  /// secure; a seeded RNG should only be used for reproducible tests.
+  ///
+  /// # Arguments
+  ///
+  /// * `elem_witnesses` - Tuples consisting of (element to prove, element's witness).
+  /// * `rng` - The source of the blinding exponent `r`.
+  pub fn prove_membership_blinded(
+    &self,
+    elem_witnesses: &[(T, Witness<G, T>)],
+    rng: &mut impl RngCore,
+  ) -> Result<BlindedMembershipProof<G, T>, AccError> {
+    let blinding_bits = 8 * G::ELEM_BYTES as u32 + 128;
+    let blinding = &random_bits(rng, blinding_bits);
+    let witness_accum = self.clone().delete(elem_witnesses)?;
+    let x: Integer = elem_witnesses
+      .iter()
+      .map(|(t, _)| hash_to_prime(t))
+      .product();
+    let blinded_witness = G::op(&witness_accum.value, &G::exp(&self.value, blinding));
+    let base = G::exp(&self.value, &x);
+    let result = G::op(&G::exp(&blinded_witness, &x), &G::inv(&self.value));
+    let proof = Poke2::prove(&base, blinding, &result);
+    Ok(BlindedMembershipProof {
+      phantom: PhantomData,
+      blinded_witness,
+      proof,
+    })
+  }
+
+  /// Verifies a blinded membership proof against the current accumulator and the elements `elems`
+  /// whose inclusion is being proven, in any order.
+  pub fn verify_membership_blinded(
+    &self,
+    elems: &[T],
+    BlindedMembershipProof {
+      blinded_witness,
+      proof,
+      ..
+    }: &BlindedMembershipProof<G, T>,
+  ) -> bool {
+    let x = prime_hash_product(elems);
+    let base = G::exp(&self.value, &x);
+    let result = G::op(&G::exp(blinded_witness, &x), &G::inv(&self.value));
+    Poke2::verify(&base, &result, proof)
+  }
+
+  /// Proves that this accumulator commits to a subset of the set committed to by `superset`,
+  /// namely that `superset` is this accumulator with `extra_elems` added. Only `extra_elems` need
+  /// to be revealed to the verifier, not the elements of the subset.
+  ///
+  /// Returns `BadWitness` if `superset` is not this accumulator raised to the prime hash product of
+  /// `extra_elems`.
+  pub fn prove_subset(&self, superset: &Self, extra_elems: &[T]) -> Result<Poe<G>, AccError> {
+    let x = prime_hash_product(extra_elems);
+    if G::exp(&self.value, &x) != superset.value {
+      return Err(AccError::BadWitness);
+    }
+    Ok(Poe::prove(&self.value, &x, &superset.value))
+  }
+
+  /// Verifies a proof from `prove_subset` that `superset` is this accumulator with `extra_elems`
+  /// added.
+  pub fn verify_subset(&self, superset: &Self, extra_elems: &[T], proof: &Poe<G>) -> bool {
+    let x = prime_hash_product(extra_elems);
+    Poe::verify(&self.value, &x, &superset.value, proof)
+  }
+
+  /// Like `prove_subset`, but hides `extra_elems` from the verifier: instead of a PoE for their
+  /// prime hash product `x`, returns a PoKE2 that the prover knows some `x` with
+  /// `self ^ x = superset`.
+  ///
+  /// Given that both accumulators were built from prime hashes, the proof convinces the verifier
+  /// that `superset` commits to a superset of this accumulator's set, since a prover knowing such
+  /// an `x` for any other value could compute roots in the group. Unlike a `prove_subset` proof, it
+  /// reveals nothing about which or how many elements were added.
+  ///
+  /// Returns `BadWitness` if `superset` is not this accumulator with `extra_elems` added.
+  pub fn prove_subset_hidden(
+    &self,
+    superset: &Self,
+    extra_elems: &[T],
+  ) -> Result<Poke2<G>, AccError> {
+    let x = prime_hash_product(extra_elems);
+    if G::exp(&self.value, &x) != superset.value {
+      return Err(AccError::BadWitness);
+    }
+    Ok(Poke2::prove(&self.value, &x, &superset.value))
+  }
+
+  /// Verifies a proof from `prove_subset_hidden` that `superset` is this accumulator with some
+  /// elements added.
+  pub fn verify_subset_hidden(&self, superset: &Self, proof: &Poke2<G>) -> bool {
+    Poke2::verify(&self.value, &superset.value, proof)
+  }
+
+  /// Proves that this accumulator and `other` commit to the same set, as the special case of
+  /// `prove_subset` with no extra elements: a PoE that `other` is this accumulator raised to `1`.
+  ///
+  /// Accumulators built from the same base commit to the same set exactly when their values are
+  /// equal (barring a collision of prime hash products, which would break the accumulator anyway),
+  /// however the elements were batched or ordered, so the proof amounts to that comparison and
+  /// reveals nothing about the set. It does not apply to accumulators over different bases (see
+  /// `setup_with_base`), whose values differ for the same set.
+  ///
+  /// Returns `BadWitness` if the accumulators differ.
+  pub fn prove_equal(&self, other: &Self) -> Result<Poe<G>, AccError> {
+    self.prove_subset(other, &[])
+  }
+
+  /// Verifies a proof from `prove_equal` that this accumulator and `other` commit to the same set.
+  pub fn verify_equal(&self, other: &Self, proof: &Poe<G>) -> bool {
+    self.verify_subset(other, &[], proof)
   }
 
   /// Updates a `witness` for `tracked_elems` w.r.t the current accumulator, adding the elements in
@@ -250,7 +880,7 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
       }
     }
 
-    let (gcd, a, b) = <(Integer, Integer, Integer)>::from(x.gcd_cofactors_ref(&x_hat));
+    let (gcd, a, b) = bezout(&x, &x_hat);
     assert!(gcd == int(1));
 
     let w = witness.0.add(untracked_additions);
@@ -269,41 +899,152 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
   ///
   /// * `acc_set` - The set of elements committed to by this accumulator.
   /// * `elems` - The set of elements you want to prove are not in `acc_set`.
+  ///
+  /// Returns `InputsNotCoprime` if some element of `elems` is in `acc_set`, and `EmptyElems` if
+  /// `elems` is empty. If `acc_set` is empty, every element is a non-member.
   pub fn prove_nonmembership(
     &self,
     acc_set: &[T],
     elems: &[T],
   ) -> Result<NonmembershipProof<G, T>, AccError> {
-    let x: Integer = elems.iter().map(hash_to_prime).product();
     let s = acc_set.iter().map(hash_to_prime).product();
-    let (gcd, a, b) = <(Integer, Integer, Integer)>::from(x.gcd_cofactors_ref(&s));
+    self.prove_nonmembership_with_product(&s, elems)
+  }
+
+  /// Like `prove_nonmembership`, but also returns the Bezout coefficients `a` and `b` with
+  /// `a * x + b * s = 1`, where `x` and `s` are the prime hash products of `elems` and `acc_set`,
+  /// so that auditors can check the relation independently.
+  ///
+  /// **Note**: The coefficients leak information about `s`, which the proof itself hides (e.g.
+  /// `s = (1 - a * x) / b`), so only hand them to trusted parties.
+  pub fn prove_nonmembership_verbose(
+    &self,
+    acc_set: &[T],
+    elems: &[T],
+  ) -> Result<(NonmembershipProof<G, T>, Integer, Integer), AccError> {
+    let s = acc_set.iter().map(hash_to_prime).product();
+    self.prove_nonmembership_(&s, elems)
+  }
+
+  /// Internal method behind `nonmembership_witness_from_product` that also returns the prime hash
+  /// product `x` of `elems` and the Bezout coefficients `a` and `b`, which are needed for the
+  /// proof.
+  #[allow(clippy::type_complexity)]
+  fn nonmembership_witness_(
+    &self,
+    set_product: &Integer,
+    elems: &[T],
+  ) -> Result<(Integer, Integer, Integer, G::Elem, G::Elem), AccError> {
+    if elems.is_empty() {
+      return Err(AccError::EmptyElems);
+    }
+    let x: Integer = elems.iter().map(hash_to_prime).product();
+    // For the empty set, fix the canonical coefficients `a = 0` and `b = 1` rather than rely on
+    // those chosen by GMP, so that the proof is `d = 1` and `v = acc` for any `elems`.
+    let (gcd, a, b) = if *set_product == 1 {
+      (int(1), int(0), int(1))
+    } else {
+      bezout(&x, set_product)
+    };
 
     if gcd != int(1) {
       return Err(AccError::InputsNotCoprime);
     }
 
-    let g = G::unknown_order_elem();
-    let d = G::exp(&g, &a);
+    let d = G::exp_unknown_order_elem(&a);
     let v = G::exp(&self.value, &b);
-    let gv_inv = G::op(&g, &G::inv(&v));
+    Ok((x, a, b, d, v))
+  }
+
+  /// Computes the LLX non-membership witness `(d, v)` for `elems` directly from the prime hash
+  /// product `set_product` of the set committed to by this accumulator, where `ax + bs = 1`,
+  /// `d = g ^ a` and `v = acc ^ b`, such that `d ^ x * v = g`.
+  ///
+  /// Returns `InputsNotCoprime` if some element of `elems` is in the set, and `EmptyElems` if
+  /// `elems` is empty.
+  pub fn nonmembership_witness_from_product(
+    &self,
+    set_product: &Integer,
+    elems: &[T],
+  ) -> Result<(G::Elem, G::Elem), AccError> {
+    let (_, _, _, d, v) = self.nonmembership_witness_(set_product, elems)?;
+    Ok((d, v))
+  }
+
+  /// A specialized version of `prove_nonmembership` for callers that already track the prime hash
+  /// product of the elements committed to by this accumulator, which avoids rehashing `acc_set`.
+  ///
+  /// # Arguments
+  ///
+  /// * `set_product` - The prime hash product of the set committed to by this accumulator.
+  /// * `elems` - The set of elements you want to prove are not in the accumulated set.
+  pub fn prove_nonmembership_with_product(
+    &self,
+    set_product: &Integer,
+    elems: &[T],
+  ) -> Result<NonmembershipProof<G, T>, AccError> {
+    Ok(self.prove_nonmembership_(set_product, elems)?.0)
+  }
+
+  /// Internal method behind `prove_nonmembership_with_product` that also returns the Bezout
+  /// coefficients `a` and `b`.
+  fn prove_nonmembership_(
+    &self,
+    set_product: &Integer,
+    elems: &[T],
+  ) -> Result<(NonmembershipProof<G, T>, Integer, Integer), AccError> {
+    let (x, a, b, d, v) = self.nonmembership_witness_(set_product, elems)?;
+    let gv_inv = G::op(&G::unknown_order_elem(), &G::inv(&v));
 
-    let poke2_proof = Poke2::prove(&self.value, &b, &v);
-    let poe_proof = Poe::prove(&d, &x, &gv_inv);
-    Ok(NonmembershipProof {
+    let z = Poke2::<G>::commitment(&b);
+    let (l_poke2, alpha, l_poe) = self.nonmembership_challenges(&x, &d, &v, &z);
+    let poke2_proof = Poke2::prove_with_challenges(&self.value, &b, z, &l_poke2, &alpha);
+    let poe_proof = Poe::prove_with_challenge(&d, &x, &l_poe);
+    let proof = NonmembershipProof {
       phantom: PhantomData,
       d,
       v,
       gv_inv,
       poke2_proof,
       poe_proof,
-    })
+    };
+    Ok((proof, a, b))
   }
 
   /// Verifies a non-membership proof against the current accumulator and elements `elems` whose
   /// non-inclusion is being proven.
-  pub fn verify_nonmembership(
-    &self,
-    elems: &[T],
+  ///
+  /// With the `nonmembership-cache` feature, results are memoized per thread by the group, the
+  /// accumulator `digest`, the queried elements and the proof, so that repeated identical queries
+  /// skip verification. Results for different accumulators are kept side by side, and the least
+  /// recently used one is evicted once `NONMEMBERSHIP_CACHE_CAPACITY` results are stored.
+  pub fn verify_nonmembership(&self, elems: &[T], proof: &NonmembershipProof<G, T>) -> bool {
+    let x = elems.iter().map(hash_to_prime).product();
+    #[cfg(feature = "nonmembership-cache")]
+    {
+      // The digest does not bind the group, so two groups with equally wide elements could
+      // otherwise share entries.
+      let key = (
+        self.digest(),
+        hash(&Blake2b::default, &(G::descriptor(), &x, proof)),
+      );
+      let cached = NONMEMBERSHIP_CACHE.with(|cache| cache.borrow_mut().get(&key));
+      if let Some(result) = cached {
+        return result;
+      }
+      let result = self.verify_nonmembership_(&x, proof);
+      NONMEMBERSHIP_CACHE.with(|cache| cache.borrow_mut().insert(key, result));
+      result
+    }
+    #[cfg(not(feature = "nonmembership-cache"))]
+    self.verify_nonmembership_(&x, proof)
+  }
+
+  /// The uncached verification behind `verify_nonmembership`, for elements with prime hash product
+  /// `x`.
+  fn verify_nonmembership_(
+    &self,
+    x: &Integer,
     NonmembershipProof {
       d,
       v,
@@ -313,8 +1054,92 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> Accumulator<G, T> {
       ..
     }: &NonmembershipProof<G, T>,
   ) -> bool {
-    let x = elems.iter().map(hash_to_prime).product();
-    Poke2::verify(&self.value, v, poke2_proof) && Poe::verify(d, &x, gv_inv, poe_proof)
+    // `gv_inv` is determined by `v`. Were it taken from the proof as is, the PoE could be for an
+    // arbitrary `d ^ x`, unrelated to the `v` that the PoKE2 is about.
+    if *gv_inv != G::op(&G::unknown_order_elem(), &G::inv(v)) {
+      return false;
+    }
+    let (l_poke2, alpha, l_poe) = self.nonmembership_challenges(x, d, v, poke2_proof.z());
+    Poke2::verify_with_challenges(&self.value, v, poke2_proof, &l_poke2, &alpha).is_ok()
+      && Poe::verify_with_challenge(d, x, gv_inv, poe_proof, &l_poe)
+  }
+
+  /// Returns the challenges `(l, alpha)` of the PoKE2 and `l` of the PoE in a non-membership proof,
+  /// all derived from one transcript of the group descriptor, the accumulator, the prime hash
+  /// product `x` of the elements, the witness `(d, v)` and the PoKE2 commitment `z`. This binds
+  /// both proofs to the whole statement, and serializes each public input once. (`g * v ^ -1` is
+  /// determined by `v`.)
+  fn nonmembership_challenges(
+    &self,
+    x: &Integer,
+    d: &G::Elem,
+    v: &G::Elem,
+    z: &G::Elem,
+  ) -> (Integer, Integer, Integer) {
+    let bits = SecurityLevel::default().challenge_bits();
+    let mut transcript = Transcript::new(NONMEMBERSHIP_DOMAIN);
+    transcript.append_descriptor::<G>();
+    transcript.append_elem::<G>(&self.value);
+    transcript.append_bigint(x);
+    transcript.append_elem::<G>(d);
+    transcript.append_elem::<G>(v);
+    transcript.append_elem::<G>(z);
+    let mut poke2_transcript = transcript.clone();
+    poke2_transcript.append_bytes(b"poke2");
+    let l_poke2 = poke2_transcript.challenge_prime(bits);
+    poke2_transcript.append_bigint(&l_poke2);
+    let alpha = poke2_transcript.challenge_scalar();
+    transcript.append_bytes(b"poe");
+    let l_poe = transcript.challenge_prime(bits);
+    (l_poke2, alpha, l_poe)
+  }
+}
+
+impl<'a, G: UnknownOrderGroup> Accumulator<G, &'a [u8]> {
+  /// Accumulates the raw data `item` and `other_items` onto `base` and proves membership of `item`,
+  /// returning the prime `item` hashes to, the accumulator, and the proof.
+  ///
+  /// The verifier can check the proof with `verify_membership`, or with `verify_membership_prime`
+  /// after recomputing the prime from `item` itself.
+  pub fn prove_membership_data(
+    base: &G::Elem,
+    item: &'a [u8],
+    other_items: &[&'a [u8]],
+  ) -> (Integer, Self, MembershipProof<G, &'a [u8]>) {
+    let prime = hash_to_prime(item);
+    let witness = Self::new_from(G::exp(base, &prime_hash_product(other_items)));
+    let acc = Self::new_from(G::exp(&witness.value, &prime));
+    let proof = Poe::prove(&witness.value, &prime, &acc.value);
+    (
+      prime,
+      acc,
+      MembershipProof {
+        witness: Witness(witness),
+        proof,
+      },
+    )
+  }
+}
+
+impl<G: UnknownOrderGroup, T: Hash> Accumulator<G, T> {
+  /// Verifies a membership proof for an element that hashes to `prime`.
+  ///
+  /// **Note**: The proof holds for any exponent the witness satisfies, so the verifier must derive
+  /// `prime` from the element itself via `hash_to_prime` rather than trust a prime sent by the
+  /// prover.
+  pub fn verify_membership_prime(
+    &self,
+    prime: &Integer,
+    MembershipProof { witness, proof }: &MembershipProof<G, T>,
+  ) -> bool {
+    Poe::verify(&witness.0.value, prime, &self.value, proof)
+  }
+}
+
+/// Returns the empty accumulator, as `Accumulator::empty`.
+impl<G: UnknownOrderGroup, T: Eq + Hash> Default for Accumulator<G, T> {
+  fn default() -> Self {
+    Self::empty()
   }
 }
 
@@ -324,6 +1149,72 @@ impl<G: UnknownOrderGroup, T: Eq + Hash> From<&[T]> for Accumulator<G, T> {
   }
 }
 
+/// Builds the accumulator of raw primes, i.e. `g ^ (p_1 * ... * p_n)`. The items must be primes,
+/// typically the results of `hash_to_prime`, since the accumulator only binds to the product: the
+/// primes are not hashed again. Membership of such a prime can be verified with
+/// `verify_membership_prime`.
+impl<G: UnknownOrderGroup, T: Eq + Hash> FromIterator<Integer> for Accumulator<G, T> {
+  fn from_iter<I: IntoIterator<Item = Integer>>(primes: I) -> Self {
+    let product: Integer = primes.into_iter().product();
+    Self::new_from(G::exp(&G::unknown_order_elem(), &product))
+  }
+}
+
+impl<G: UnknownOrderGroup, T: Hash> MembershipProof<G, T> {
+  /// Drops the PoE, leaving a `CompactMembershipProof` verified by `verify_membership_compact`.
+  ///
+  /// The PoE only saves the verifier work when the exponent is much longer than its challenge, as
+  /// for a batch of many elements. For a single element, whose prime is as long as the challenge,
+  /// checking `witness ^ x = acc` directly costs about as much as verifying the PoE, so the smaller
+  /// compact proof is preferable whenever the verifier knows the element.
+  pub fn compact(self) -> CompactMembershipProof<G, T> {
+    CompactMembershipProof {
+      witness: self.witness,
+    }
+  }
+
+  /// Returns the PoE that the witness raised to the prime hash product of the proven elements is
+  /// the accumulator.
+  pub fn poe(&self) -> &Poe<G> {
+    &self.proof
+  }
+
+  /// Returns the byte encoding of the proof: `G::to_bytes` of the witness followed by the encoding
+  /// of the PoE.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = G::to_bytes(&self.witness.0.value);
+    bytes.extend(self.proof.to_bytes());
+    bytes
+  }
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. Returns `None` if `bytes` is
+  /// malformed; see the `TryFrom<&[u8]>` impl for the reason.
+  pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    Self::try_from(bytes).ok()
+  }
+}
+
+impl<G: UnknownOrderGroup, T: Hash> TryFrom<&[u8]> for MembershipProof<G, T> {
+  type Error = ProofError;
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. The length of `bytes` is checked
+  /// against the element size of `G` before anything is decoded.
+  fn try_from(bytes: &[u8]) -> Result<Self, ProofError> {
+    if bytes.len() != 2 * G::ELEM_BYTES {
+      return Err(ProofError::BadLength);
+    }
+    let (witness_bytes, proof_bytes) = bytes.split_at(G::ELEM_BYTES);
+    let value = G::from_bytes(witness_bytes).ok_or(ProofError::BadElem)?;
+    Ok(Self {
+      witness: Witness(Accumulator {
+        phantom: PhantomData,
+        value,
+      }),
+      proof: Poe::try_from(proof_bytes)?,
+    })
+  }
+}
+
 impl<G: UnknownOrderGroup, T: Clone + Hash> Witness<G, T> {
   /// Given a witness for `witness_set`, returns a witness for `witness_subset`.
   ///
@@ -367,8 +1258,22 @@ impl<G: UnknownOrderGroup, T: Clone + Hash> Witness<G, T> {
       .collect()
   }
 
+  /// Like `compute_individual_witnesses`, but shares the squarings of each intermediate witness
+  /// between the two halves it is split into, via `exp_pair`, in constant extra memory.
+  ///
+  /// Faster for groups whose `exp` is plain square-and-multiply, such as `ClassGroup`, and slower
+  /// for groups with a windowed `exp`, such as `Rsa2048`. See `benches/accumulator/witnesses.rs`.
+  pub fn compute_individual_witnesses_cached(&self, elems: &[T]) -> Vec<(T, Self)> {
+    let hashes = elems.iter().map(hash_to_prime).collect::<Vec<_>>();
+    elems
+      .iter()
+      .cloned()
+      .zip(self.root_factor_cached(&hashes))
+      .collect()
+  }
+
   #[allow(non_snake_case)]
-  fn root_factor(&self, elems: &[Integer]) -> Vec<Self> {
+  pub(crate) fn root_factor(&self, elems: &[Integer]) -> Vec<Self> {
     if elems.len() == 1 {
       return vec![self.clone()];
     }
@@ -390,37 +1295,44 @@ impl<G: UnknownOrderGroup, T: Clone + Hash> Witness<G, T> {
     L.append(&mut R);
     L
   }
+
+  /// Like `root_factor`, but raises `self` to the product of each half via one `exp_pair`.
+  #[allow(non_snake_case)]
+  fn root_factor_cached(&self, elems: &[Integer]) -> Vec<Self> {
+    if elems.len() == 1 {
+      return vec![self.clone()];
+    }
+    let half_n = elems.len() / 2;
+    let (g_l, g_r) = exp_pair::<G>(
+      &self.0.value,
+      &elems[..half_n].iter().product(),
+      &elems[half_n..].iter().product(),
+    );
+    let g_l = Self(Accumulator {
+      phantom: PhantomData,
+      value: g_l,
+    });
+    let g_r = Self(Accumulator {
+      phantom: PhantomData,
+      value: g_r,
+    });
+    let mut L = g_r.root_factor_cached(&elems[..half_n]);
+    let mut R = g_l.root_factor_cached(&elems[half_n..]);
+    L.append(&mut R);
+    L
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::group::{ClassGroup, Rsa2048};
+  use crate::group::{DummyRsa, DummyRsa2, DummyRsaVar, ElemFrom, Group, Rsa2048};
+  use crate::util::test_rng;
 
   fn new_acc<G: UnknownOrderGroup, T: Hash + Eq>(data: &[T]) -> Accumulator<G, T> {
     Accumulator::<G, T>::empty().add(data)
   }
 
-  macro_rules! test_all_groups {
-    ($test_func:ident, $func_name_rsa:ident, $func_name_class:ident, $($attr:meta)*) => {
-      #[test]
-      $(
-        #[$attr]
-      )*
-      fn $func_name_rsa() {
-        $test_func::<Rsa2048>();
-      }
-
-      #[test]
-      $(
-        #[$attr]
-      )*
-      fn $func_name_class() {
-        $test_func::<ClassGroup>();
-      }
-    };
-  }
-
   test_all_groups!(test_add, test_add_rsa2048, test_add_class,);
   fn test_add<G: UnknownOrderGroup>() {
     let acc = new_acc::<G, &'static str>(&["a", "b"]);
@@ -434,6 +1346,258 @@ mod tests {
     assert!(acc_new.verify_membership_batch(&new_elems, &proof));
   }
 
+  test_all_groups!(test_genesis, test_genesis_rsa2048, test_genesis_class,);
+  fn test_genesis<G: UnknownOrderGroup>() {
+    let initial_elems = ["a", "b", "c"];
+    let (acc, proof) = Accumulator::<G, &'static str>::genesis(&initial_elems);
+    assert!(acc == new_acc(&initial_elems));
+    assert!(acc.verify_genesis(&initial_elems, &proof));
+    assert!(!acc.verify_genesis(&["a", "b"], &proof));
+    assert!(!new_acc::<G, &'static str>(&["a", "b", "d"]).verify_genesis(&initial_elems, &proof));
+  }
+
+  test_all_groups!(
+    test_empty_with_base,
+    test_empty_with_base_rsa2048,
+    test_empty_with_base_class,
+  );
+  fn test_empty_with_base<G: UnknownOrderGroup>() {
+    let acc_1 = Accumulator::<G, &'static str>::empty_with_base(b"first").add(&["a", "b"]);
+    let acc_2 = Accumulator::<G, &'static str>::empty_with_base(b"second").add(&["a", "b"]);
+    assert!(acc_1.value != acc_2.value);
+    let (acc_1, proof_1) = acc_1.add_with_proof(&["c"]);
+    let (acc_2, proof_2) = acc_2.add_with_proof(&["c"]);
+    assert!(acc_1.verify_membership(&"c", &proof_1));
+    assert!(acc_2.verify_membership(&"c", &proof_2));
+    assert!(!acc_1.verify_membership(&"c", &proof_2));
+    assert!(!acc_2.verify_membership(&"c", &proof_1));
+    assert!(!new_acc::<G, &'static str>(&["a", "b", "c"]).verify_membership(&"c", &proof_1));
+  }
+
+  test_all_groups!(
+    test_prove_membership_aggregate,
+    test_prove_membership_aggregate_rsa2048,
+    test_prove_membership_aggregate_class,
+  );
+  fn test_prove_membership_aggregate<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d"];
+    let acc = new_acc::<G, &'static str>(&elems);
+    let witnesses = Witness(Accumulator::<G, &'static str>::empty())
+      .compute_individual_witnesses(&elems)
+      .into_iter()
+      .map(|(elem, witness)| (hash_to_prime(&elem), witness.0.value))
+      .collect::<Vec<_>>();
+    let prime_witnesses = witnesses.iter().map(|(p, w)| (p, w)).collect::<Vec<_>>();
+    let (product, proof) = acc.prove_membership_aggregate(&prime_witnesses).unwrap();
+    assert!(product == prime_hash_product(&elems));
+    assert!(acc.verify_membership_prime(&product, &proof));
+    assert!(acc.verify_membership_batch(&elems, &proof));
+    assert!(!acc.verify_membership_batch(&elems[..3], &proof));
+
+    let bad_witness = G::unknown_order_elem();
+    let mut bad = prime_witnesses.clone();
+    bad[2].1 = &bad_witness;
+    assert!(matches!(
+      acc.prove_membership_aggregate(&bad),
+      Err(AccError::BadWitness)
+    ));
+    let mut repeated = prime_witnesses.clone();
+    repeated[3] = repeated[0];
+    assert!(matches!(
+      acc.prove_membership_aggregate(&repeated),
+      Err(AccError::InputsNotCoprime)
+    ));
+    assert!(matches!(
+      acc.prove_membership_aggregate(&[]),
+      Err(AccError::EmptyElems)
+    ));
+  }
+
+  #[test]
+  fn test_prove_subset_hidden() {
+    let subset = new_acc::<Rsa2048, &'static str>(&["a", "b"]);
+    let extra_elems = ["c", "d"];
+    let superset = subset.clone().add(&extra_elems);
+    let proof = subset.prove_subset_hidden(&superset, &extra_elems).unwrap();
+    assert!(subset.verify_subset_hidden(&superset, &proof));
+    assert!(!superset.verify_subset_hidden(&subset, &proof));
+    assert!(subset.prove_subset_hidden(&superset, &["c"]).is_err());
+
+    // Neither the extra primes nor their product appear anywhere in the encoded proof.
+    let proof_bytes = proof.to_bytes();
+    let hidden = extra_elems
+      .iter()
+      .map(hash_to_prime)
+      .chain(std::iter::once(prime_hash_product(&extra_elems)));
+    for secret in hidden {
+      let secret_bytes = secret.to_digits::<u8>(rug::integer::Order::Msf);
+      assert!(!proof_bytes
+        .windows(secret_bytes.len())
+        .any(|w| w == &secret_bytes[..]));
+    }
+  }
+
+  test_all_groups!(
+    test_prove_equal,
+    test_prove_equal_rsa2048,
+    test_prove_equal_class,
+  );
+  fn test_prove_equal<G: UnknownOrderGroup>() {
+    let acc_a = new_acc::<G, &'static str>(&["a", "b", "c"]);
+    let acc_b = new_acc::<G, &'static str>(&["c"]).add(&["b", "a"]);
+    let proof = acc_a.prove_equal(&acc_b).unwrap();
+    assert!(acc_a.verify_equal(&acc_b, &proof));
+    assert!(acc_b.verify_equal(&acc_a, &proof));
+
+    let acc_c = new_acc::<G, &'static str>(&["a", "b"]);
+    match acc_a.prove_equal(&acc_c) {
+      Err(AccError::BadWitness) => {}
+      _ => panic!("expected BadWitness"),
+    }
+    assert!(!acc_a.verify_equal(&acc_c, &proof));
+    assert!(!acc_c.verify_equal(&acc_a, &acc_c.prove_equal(&acc_c).unwrap()));
+  }
+
+  #[test]
+  fn test_default() {
+    let acc = Accumulator::<DummyRsa, &'static str>::default();
+    assert!(acc == Accumulator::empty());
+    // The empty product: the accumulator is the bare generator.
+    assert!(acc.value == DummyRsa::unknown_order_elem());
+    assert!(Accumulator::<Rsa2048, &'static str>::default() == Accumulator::empty());
+  }
+
+  test_all_groups!(
+    test_add_stepwise,
+    test_add_stepwise_rsa2048,
+    test_add_stepwise_class,
+  );
+  fn test_add_stepwise<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b"]);
+    let new_elems = ["c", "d", "e"];
+    let steps = acc.clone().add_stepwise(&new_elems);
+    assert_eq!(steps.len(), new_elems.len());
+    for ((acc_i, proof), elem) in steps.iter().zip(new_elems.iter()) {
+      assert!(acc_i.verify_membership(elem, proof));
+    }
+    assert!(steps.last().unwrap().0 == acc.add(&new_elems));
+  }
+
+  test_all_groups!(
+    test_add_delete_flow,
+    test_add_delete_flow_rsa2048,
+    test_add_delete_flow_class,
+  );
+  fn test_add_delete_flow<G: UnknownOrderGroup>() {
+    let acc_0 = new_acc::<G, &'static str>(&["a"]);
+    let (acc_1, proof) = acc_0.clone().add_with_proof(&["b", "c"]);
+    assert!(acc_1.verify_membership_batch(&["b", "c"], &proof));
+    let witnesses = proof.witness.compute_individual_witnesses(&["b", "c"]);
+    let (acc_2, proof) = acc_1
+      .clone()
+      .delete_with_proof(&witnesses)
+      .expect("valid delete expected");
+    assert!(acc_2 == acc_0);
+    assert!(acc_1.verify_membership_batch(&["b", "c"], &proof));
+  }
+
+  #[test]
+  fn test_add_delete_flow_dummy() {
+    // `4 ^ H(a) mod N`, with the sign normalized as in `DummyRsa::elem`.
+    let value = int(4)
+      .pow_mod(&hash_to_prime(&"a"), DummyRsa::modulus())
+      .unwrap();
+    assert!(new_acc::<DummyRsa, &'static str>(&["a"]).value == DummyRsa::elem(value));
+    test_add_delete_flow::<DummyRsa>();
+    test_add_delete_flow::<DummyRsa2>();
+  }
+
+  #[test]
+  fn test_add_delete_flow_random_moduli() {
+    let mut rng = test_rng(0);
+    for _ in 0..50 {
+      let modulus = DummyRsaVar::random_modulus(&mut rng, 16);
+      DummyRsaVar::with_modulus(modulus, test_add_delete_flow::<DummyRsaVar>);
+    }
+  }
+
+  test_all_groups!(test_digest, test_digest_rsa2048, test_digest_class,);
+  fn test_digest<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b"]);
+    let acc_reordered = new_acc::<G, &'static str>(&["b"]).add(&["a"]);
+    assert_eq!(acc.digest(), acc_reordered.digest());
+    assert_ne!(acc.digest(), acc.clone().add(&["c"]).digest());
+    assert_ne!(
+      acc.digest(),
+      Accumulator::<G, &'static str>::empty().digest()
+    );
+  }
+
+  test_all_groups!(
+    test_verify_membership_bundle,
+    test_verify_membership_bundle_rsa2048,
+    test_verify_membership_bundle_class,
+  );
+  fn test_verify_membership_bundle<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b"]);
+    let (acc, proof) = acc.add_with_proof(&["c"]);
+    let bundle = proof.to_bytes();
+    assert!(MembershipProof::from_bytes(&bundle) == Some(proof));
+    assert_eq!(acc.verify_membership_bundle(&"c", &bundle), Ok(true));
+    assert_eq!(acc.verify_membership_bundle(&"d", &bundle), Ok(false));
+    assert_eq!(
+      acc.verify_membership_bundle(&"c", &bundle[1..]),
+      Err(ProofError::BadLength)
+    );
+    let mut bad_elem = bundle.clone();
+    for b in &mut bad_elem[G::ELEM_BYTES..] {
+      *b = 0xff;
+    }
+    assert_eq!(
+      acc.verify_membership_bundle(&"c", &bad_elem),
+      Err(ProofError::BadElem)
+    );
+  }
+
+  #[test]
+  fn test_add_with_proof_in_context() {
+    let acc = new_acc::<Rsa2048, &'static str>(&["a"]);
+    let (acc, proof) = acc.add_with_proof_in_context(&["b", "c"], b"block A");
+    assert!(acc.verify_membership_batch_in_context(&["b", "c"], &proof, b"block A"));
+    assert!(!acc.verify_membership_batch_in_context(&["b", "c"], &proof, b"block B"));
+    assert!(!acc.verify_membership_batch(&["b", "c"], &proof));
+    let (_, proof_no_context) =
+      new_acc::<Rsa2048, &'static str>(&["a"]).add_with_proof(&["b", "c"]);
+    assert!(acc.verify_membership_batch_in_context(&["b", "c"], &proof_no_context, &[]));
+  }
+
+  test_all_groups!(test_replay, test_replay_rsa2048, test_replay_class,);
+  fn test_replay<G: UnknownOrderGroup>() {
+    let genesis = new_acc::<G, &'static str>(&["a"]);
+    let acc_1 = genesis.clone().add(&["b", "c"]);
+    let acc_2 = acc_1.clone().add(&["d"]);
+    let b_witness = Witness(genesis.clone().add(&["c", "d"]));
+    let acc_3 = acc_2.clone().delete(&[("b", b_witness.clone())]).unwrap();
+    let acc_4 = acc_3.clone().add(&["e"]);
+    let ops = vec![
+      AccOp::Add(vec!["b", "c"]),
+      AccOp::Add(vec!["d"]),
+      AccOp::Delete(vec![("b", b_witness.clone())]),
+      AccOp::Add(vec!["e"]),
+    ];
+    assert!(genesis.clone().replay(&ops).unwrap() == acc_4);
+    assert!(genesis.clone().replay(&ops[..2]).unwrap() == acc_2);
+    assert!(genesis.clone().replay(&[]).unwrap() == genesis);
+
+    // The witness for `b` is stale after `b` was deleted once.
+    let mut bad_ops = ops.clone();
+    bad_ops.push(AccOp::Delete(vec![("b", b_witness)]));
+    match genesis.replay(&bad_ops) {
+      Err(AccError::BadWitness) => {}
+      _ => panic!("expected BadWitness"),
+    }
+  }
+
   test_all_groups!(test_delete, test_delete_rsa2048, test_delete_class,);
   fn test_delete<G: UnknownOrderGroup>() {
     let acc_0 = new_acc::<G, &'static str>(&["a", "b"]);
@@ -446,6 +1610,237 @@ mod tests {
     assert!(acc_1.verify_membership(&"c", &proof));
   }
 
+  test_all_groups!(
+    test_compact_membership_proof,
+    test_compact_membership_proof_rsa2048,
+    test_compact_membership_proof_class,
+  );
+  fn test_compact_membership_proof<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b"]);
+    let (acc, proof) = acc.add_with_proof(&["c"]);
+    let full_len = G::to_bytes(&proof.witness.0.value).len() + proof.poe().to_bytes().len();
+    let compact = proof.compact();
+    assert_eq!(
+      G::to_bytes(&compact.witness.0.value).len(),
+      full_len - G::ELEM_BYTES
+    );
+    assert!(acc.verify_membership_compact(&"c", &compact));
+    assert!(!acc.verify_membership_compact(&"a", &compact));
+  }
+
+  test_all_groups!(
+    test_verify_deletion,
+    test_verify_deletion_rsa2048,
+    test_verify_deletion_class,
+  );
+  fn test_verify_deletion<G: UnknownOrderGroup>() {
+    let acc_0 = new_acc::<G, &'static str>(&["a", "b"]);
+    let acc_1 = acc_0.clone().add(&["c", "d"]);
+    let (acc_2, proof) = acc_1
+      .clone()
+      .delete_with_proof(&[
+        ("c", Witness(acc_0.clone().add(&["d"]))),
+        ("d", Witness(acc_0.clone().add(&["c"]))),
+      ])
+      .expect("valid delete expected");
+    let deleted_product = prime_hash_product(&["c", "d"]);
+    assert!(acc_1.verify_deletion(&acc_2, &deleted_product, proof.poe()));
+    let tampered = acc_0.add(&["e"]);
+    assert!(!acc_1.verify_deletion(&tampered, &deleted_product, proof.poe()));
+    assert!(!acc_1.verify_deletion(&acc_2, &prime_hash_product(&["c"]), proof.poe()));
+  }
+
+  test_all_groups!(
+    test_prove_membership_blinded,
+    test_prove_membership_blinded_rsa2048,
+    test_prove_membership_blinded_class,
+  );
+  fn test_prove_membership_blinded<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b", "c"]);
+    let witness = Witness(new_acc::<G, &'static str>(&["a", "b"]));
+    let prove = |seed| {
+      acc
+        .prove_membership_blinded(&[("c", witness.clone())], &mut test_rng(seed))
+        .expect("valid proof expected")
+    };
+    let proof_1 = prove(1);
+    let proof_2 = prove(2);
+    assert!(prove(1) == proof_1);
+    assert!(acc.verify_membership_blinded(&["c"], &proof_1));
+    assert!(acc.verify_membership_blinded(&["c"], &proof_2));
+    assert!(!acc.verify_membership_blinded(&["a"], &proof_1));
+    assert!(G::to_bytes(&proof_1.blinded_witness) != G::to_bytes(&proof_2.blinded_witness));
+    assert!(proof_1.proof.to_bytes() != proof_2.proof.to_bytes());
+    assert!(G::to_bytes(&proof_1.blinded_witness) != G::to_bytes(&witness.0.value));
+    assert!(acc
+      .prove_membership_blinded(&[("d", witness)], &mut test_rng(1))
+      .is_err());
+  }
+
+  test_all_groups!(
+    test_prove_membership_blinded_batch,
+    test_prove_membership_blinded_batch_rsa2048,
+    test_prove_membership_blinded_batch_class,
+  );
+  fn test_prove_membership_blinded_batch<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b", "c"]);
+    let elem_witnesses = [
+      ("b", Witness(new_acc::<G, &'static str>(&["a", "c"]))),
+      ("c", Witness(new_acc::<G, &'static str>(&["a", "b"]))),
+    ];
+    let proof = acc
+      .prove_membership_blinded(&elem_witnesses, &mut test_rng(1))
+      .expect("valid proof expected");
+    assert!(acc.verify_membership_blinded(&["b", "c"], &proof));
+    assert!(acc.verify_membership_blinded(&["c", "b"], &proof));
+    assert!(!acc.verify_membership_blinded(&["b"], &proof));
+    assert!(!acc.verify_membership_blinded(&["a", "c"], &proof));
+  }
+
+  test_all_groups!(
+    test_verify_membership_batch_elems,
+    test_verify_membership_batch_elems_rsa2048,
+    test_verify_membership_batch_elems_class,
+  );
+  fn test_verify_membership_batch_elems<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a"]);
+    let (acc, proof) = acc.add_with_proof(&["b", "c"]);
+    assert!(acc.verify_membership_batch(&["b", "c"], &proof));
+    assert!(acc.verify_membership_batch(&["c", "b"], &proof));
+    assert!(!acc.verify_membership_batch(&["b"], &proof));
+    assert!(!acc.verify_membership_batch(&["a", "b", "c"], &proof));
+    assert!(!acc.verify_membership_batch(&["b", "d"], &proof));
+  }
+
+  test_all_groups!(test_subset, test_subset_rsa2048, test_subset_class,);
+  fn test_subset<G: UnknownOrderGroup>() {
+    let acc_a = new_acc::<G, &'static str>(&["a", "b"]);
+    let acc_b = acc_a.clone().add(&["c", "d"]);
+    let proof = acc_a
+      .prove_subset(&acc_b, &["c", "d"])
+      .expect("valid proof expected");
+    assert!(acc_a.verify_subset(&acc_b, &["c", "d"], &proof));
+    assert!(acc_a.verify_subset(&acc_b, &["d", "c"], &proof));
+    assert!(!acc_a.verify_subset(&acc_b, &["c", "e"], &proof));
+    assert!(!acc_a.verify_subset(&acc_b, &["c"], &proof));
+    assert!(!acc_b.verify_subset(&acc_a, &["c", "d"], &proof));
+    assert!(acc_a.prove_subset(&acc_b, &["c", "e"]).is_err());
+    assert!(acc_b.prove_subset(&acc_a, &["c", "d"]).is_err());
+  }
+
+  /// Returns all orderings of `xs`.
+  fn permutations<X: Clone>(xs: &[X]) -> Vec<Vec<X>> {
+    if xs.is_empty() {
+      return vec![vec![]];
+    }
+    let mut perms = Vec::new();
+    for i in 0..xs.len() {
+      let mut rest = xs.to_vec();
+      let x = rest.remove(i);
+      for mut perm in permutations(&rest) {
+        perm.insert(0, x.clone());
+        perms.push(perm);
+      }
+    }
+    perms
+  }
+
+  test_all_groups!(
+    test_delete_order,
+    test_delete_order_rsa2048,
+    test_delete_order_class,
+  );
+  fn test_delete_order<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c"];
+    let acc_0 = new_acc::<G, &'static str>(&["d"]);
+    let (acc, proof) = acc_0.clone().add_with_proof(&elems);
+    let elem_witnesses = proof.witness.compute_individual_witnesses(&elems);
+    let expected = acc
+      .clone()
+      .delete_with_proof(&elem_witnesses)
+      .expect("valid delete expected");
+    assert!(expected.0 == acc_0);
+    let perms = permutations(&elem_witnesses);
+    assert_eq!(perms.len(), 6);
+    for perm in perms {
+      let (acc_new, proof) = acc
+        .clone()
+        .delete_with_proof(&perm)
+        .expect("valid delete expected");
+      assert!(acc_new == expected.0);
+      assert!(proof == expected.1);
+      assert_eq!(proof.poe().to_bytes(), expected.1.poe().to_bytes());
+      assert_eq!(
+        G::to_bytes(&proof.witness.0.value),
+        G::to_bytes(&expected.1.witness.0.value)
+      );
+    }
+    assert!(acc.verify_membership_batch(&elems, &expected.1));
+  }
+
+  test_all_groups!(
+    test_prove_membership_data,
+    test_prove_membership_data_rsa2048,
+    test_prove_membership_data_class,
+  );
+  fn test_prove_membership_data<G: UnknownOrderGroup>() {
+    let item: &[u8] = b"item";
+    let other_items: [&[u8]; 2] = [b"other", b"items"];
+    let (prime, acc, proof) =
+      Accumulator::<G, &[u8]>::prove_membership_data(&G::unknown_order_elem(), item, &other_items);
+    assert!(prime == hash_to_prime(item));
+    assert!(acc == Accumulator::empty().add(&[item, other_items[0], other_items[1]]));
+    assert!(acc.verify_membership_prime(&hash_to_prime(b"item"), &proof));
+    assert!(acc.verify_membership(&item, &proof));
+    assert!(!acc.verify_membership_prime(&hash_to_prime(b"other"), &proof));
+  }
+
+  test_all_groups!(
+    test_transition,
+    test_transition_rsa2048,
+    test_transition_class,
+  );
+  fn test_transition<G: UnknownOrderGroup>() {
+    let acc_0 = new_acc::<G, &'static str>(&["a", "b", "c"]);
+    let acc_1 = new_acc::<G, &'static str>(&["b", "d", "e"]);
+    let removals = [
+      ("a", Witness(new_acc::<G, &'static str>(&["b", "c"]))),
+      ("c", Witness(new_acc::<G, &'static str>(&["a", "b"]))),
+    ];
+    let proof = acc_0
+      .prove_transition(&acc_1, &["d", "e"], &removals)
+      .expect("valid transition expected");
+    assert!(acc_0.verify_transition(&acc_1, &["d", "e"], &["a", "c"], &proof));
+    assert!(!acc_0.verify_transition(&acc_1, &["d", "e"], &["a"], &proof));
+    assert!(!acc_0.verify_transition(&acc_1, &["d"], &["a", "c"], &proof));
+    assert!(!acc_1.verify_transition(&acc_0, &["d", "e"], &["a", "c"], &proof));
+    assert!(acc_0
+      .prove_transition(&acc_1, &["d", "f"], &removals)
+      .is_err());
+  }
+
+  test_all_groups!(
+    test_apply_delta,
+    test_apply_delta_rsa2048,
+    test_apply_delta_class,
+  );
+  fn test_apply_delta<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b"]);
+    let a_witness = Witness(new_acc::<G, &'static str>(&["b"]));
+    let (acc_new, removal_proof, addition_proof) = acc
+      .clone()
+      .apply_delta(&["c"], &[("a", a_witness.clone())])
+      .expect("valid delta expected");
+    let acc_expected = acc
+      .clone()
+      .delete(&[("a", a_witness)])
+      .expect("valid delete expected")
+      .add(&["c"]);
+    assert!(acc_new == acc_expected);
+    assert!(acc.verify_membership(&"a", &removal_proof));
+    assert!(acc_new.verify_membership(&"c", &addition_proof));
+  }
+
   test_all_groups!(
     test_delete_empty,
     test_delete_empty_rsa2048,
@@ -474,6 +1869,104 @@ mod tests {
     acc.delete(&[("a", a_witness), ("b", b_witness)]).unwrap();
   }
 
+  test_all_groups!(
+    test_delete_present,
+    test_delete_present_rsa2048,
+    test_delete_present_class,
+  );
+  fn test_delete_present<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b", "c", "d"]);
+    let a_witness = Witness(new_acc::<G, &'static str>(&["b", "c", "d"]));
+    let e_witness = Witness(new_acc::<G, &'static str>(&["a", "b", "c", "d"]));
+    let c_witness = Witness(new_acc::<G, &'static str>(&["a", "b", "d"]));
+    let (acc_new, proof, skipped) =
+      acc
+        .clone()
+        .delete_present(&[("a", a_witness), ("e", e_witness), ("c", c_witness)]);
+    assert_eq!(skipped, vec![1]);
+    assert!(acc_new == new_acc::<G, &'static str>(&["b", "d"]));
+    assert!(acc.verify_membership_batch(&["a", "c"], &proof));
+  }
+
+  test_all_groups!(
+    test_delete_present_duplicate,
+    test_delete_present_duplicate_rsa2048,
+    test_delete_present_duplicate_class,
+  );
+  fn test_delete_present_duplicate<G: UnknownOrderGroup>() {
+    let acc = new_acc::<G, &'static str>(&["a", "b", "c"]);
+    let a_witness = Witness(new_acc::<G, &'static str>(&["b", "c"]));
+    let b_witness = Witness(new_acc::<G, &'static str>(&["a", "c"]));
+    let (acc_new, proof, skipped) =
+      acc
+        .clone()
+        .delete_present(&[("a", a_witness.clone()), ("b", b_witness), ("a", a_witness)]);
+    assert_eq!(skipped, vec![2]);
+    assert!(acc_new == new_acc::<G, &'static str>(&["c"]));
+    assert!(acc.verify_membership_batch(&["a", "b"], &proof));
+  }
+
+  test_all_groups!(
+    test_delete_from_product,
+    test_delete_from_product_rsa2048,
+    test_delete_from_product_class,
+  );
+  fn test_delete_from_product<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d"];
+    let acc = new_acc::<G, &'static str>(&elems);
+    let (acc_new, proof) = Accumulator::<G, &'static str>::delete_from_product(
+      &G::unknown_order_elem(),
+      &prime_hash_product(&elems),
+      &["b", "d"],
+    )
+    .expect("valid delete expected");
+    assert!(acc_new == new_acc::<G, &'static str>(&["a", "c"]));
+    assert!(acc.verify_membership_batch(&["b", "d"], &proof));
+  }
+
+  test_all_groups!(
+    test_delete_from_product_missing,
+    test_delete_from_product_missing_rsa2048,
+    test_delete_from_product_missing_class,
+    should_panic(expected = "BadWitness")
+  );
+  fn test_delete_from_product_missing<G: UnknownOrderGroup>() {
+    Accumulator::<G, &'static str>::delete_from_product(
+      &G::unknown_order_elem(),
+      &prime_hash_product(&["a", "b"]),
+      &["a", "c"],
+    )
+    .unwrap();
+  }
+
+  test_all_groups!(
+    test_prove_membership_from_product,
+    test_prove_membership_from_product_rsa2048,
+    test_prove_membership_from_product_class,
+  );
+  fn test_prove_membership_from_product<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d", "e"];
+    let product = prime_hash_product(&elems);
+    for elem in &elems {
+      let (acc, proof) = Accumulator::<G, &'static str>::prove_membership_from_product(
+        &G::unknown_order_elem(),
+        &product,
+        elem,
+      )
+      .expect("valid proof expected");
+      assert!(acc == new_acc::<G, &'static str>(&elems));
+      assert!(acc.verify_membership(elem, &proof));
+    }
+    assert!(
+      Accumulator::<G, &'static str>::prove_membership_from_product(
+        &G::unknown_order_elem(),
+        &product,
+        &"f",
+      )
+      .is_err()
+    );
+  }
+
   test_all_groups!(
     test_update_membership_witness,
     test_update_membership_witness_rsa2048,
@@ -517,6 +2010,218 @@ mod tests {
     assert!(acc.verify_nonmembership(&non_members, &proof));
   }
 
+  #[test]
+  fn test_prove_nonmembership_verbose() {
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<Rsa2048, &'static str>(&acc_set);
+    let non_members = ["c", "d"];
+    let (proof, a, b) = acc
+      .prove_nonmembership_verbose(&acc_set, &non_members)
+      .unwrap();
+    let x = prime_hash_product(&non_members);
+    let s = prime_hash_product(&acc_set);
+    assert!(a * x + b * s == 1);
+    assert!(acc.verify_nonmembership(&non_members, &proof));
+  }
+
+  test_all_groups!(
+    test_verify_nonmembership_tampered,
+    test_verify_nonmembership_tampered_rsa2048,
+    test_verify_nonmembership_tampered_class,
+  );
+  fn test_verify_nonmembership_tampered<G: UnknownOrderGroup>() {
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<G, &'static str>(&acc_set);
+    let proof = acc.prove_nonmembership(&acc_set, &["c"]).unwrap();
+    assert!(acc.verify_nonmembership(&["c"], &proof));
+    let g = G::unknown_order_elem();
+    let tamper = |elem: &G::Elem| G::op(elem, &g);
+    let mut tampered = vec![proof.clone(), proof.clone(), proof.clone(), proof.clone()];
+    tampered[0].d = tamper(&proof.d);
+    tampered[1].v = tamper(&proof.v);
+    tampered[2].gv_inv = tamper(&proof.gv_inv);
+    // Any `Q` other than the honest one, which is `d ^ (x / l)`.
+    tampered[3].poe_proof = Poe::prove(&g, &(int(1) << 600), &g);
+    assert!(tampered[3].poe_proof != proof.poe_proof);
+    for bad in &tampered {
+      assert!(!acc.verify_nonmembership(&["c"], bad));
+    }
+    // The PoKE2 is bound to the shared transcript, so it does not verify on its own.
+    assert!(!Poke2::verify(&acc.value, &proof.v, &proof.poke2_proof));
+    // A PoKE2 made for another non-membership statement is rejected too.
+    let other = acc.prove_nonmembership(&acc_set, &["d"]).unwrap();
+    let mut swapped = proof;
+    swapped.poke2_proof = other.poke2_proof;
+    assert!(!acc.verify_nonmembership(&["c"], &swapped));
+  }
+
+  #[test]
+  fn test_verify_nonmembership_forged_gv_inv() {
+    // Pick `v` with a known exponent and any `d`, then claim `gv_inv = d ^ x` rather than
+    // `g * v ^ -1`. Both subproofs are honest for their (inconsistent) statements.
+    let acc = new_acc::<Rsa2048, &'static str>(&["a", "b"]);
+    let x = hash_to_prime(&"a");
+    let b = int(5);
+    let v = Rsa2048::exp(&acc.value, &b);
+    let d = Rsa2048::elem(9);
+    let gv_inv = Rsa2048::exp(&d, &x);
+    let z = Poke2::<Rsa2048>::commitment(&b);
+    let (l_poke2, alpha, l_poe) = acc.nonmembership_challenges(&x, &d, &v, &z);
+    let forged = NonmembershipProof {
+      phantom: PhantomData,
+      poke2_proof: Poke2::prove_with_challenges(&acc.value, &b, z, &l_poke2, &alpha),
+      poe_proof: Poe::prove_with_challenge(&d, &x, &l_poe),
+      d,
+      v,
+      gv_inv,
+    };
+    assert!(!acc.verify_nonmembership(&["a"], &forged));
+  }
+
+  #[cfg(feature = "nonmembership-cache")]
+  #[test]
+  fn test_verify_nonmembership_cache() {
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<Rsa2048, &'static str>(&acc_set);
+    let proof = acc.prove_nonmembership(&acc_set, &["c"]).unwrap();
+    NONMEMBERSHIP_CACHE.with(|cache| *cache.borrow_mut() = NonmembershipCache::default());
+    assert!(acc.verify_nonmembership(&["c"], &proof));
+    NONMEMBERSHIP_CACHE.with(|cache| {
+      let cache = &mut *cache.borrow_mut();
+      assert_eq!(cache.results.len(), 1);
+      assert!(cache.results.keys().next().unwrap().0 == acc.digest());
+      // Flip the cached result, so that a query served from the cache is observable.
+      cache.results.values_mut().next().unwrap().0 = false;
+    });
+    assert!(!acc.verify_nonmembership(&["c"], &proof));
+
+    // Querying another accumulator keeps the entry for `acc`, so alternating between them hits.
+    let acc_2 = acc.clone().add(&["d"]);
+    assert!(!acc_2.verify_nonmembership(&["c"], &proof));
+    NONMEMBERSHIP_CACHE.with(|cache| assert_eq!(cache.borrow().results.len(), 2));
+    assert!(!acc.verify_nonmembership(&["c"], &proof));
+    assert!(!acc_2.verify_nonmembership(&["c"], &proof));
+    NONMEMBERSHIP_CACHE.with(|cache| assert_eq!(cache.borrow().results.len(), 2));
+  }
+
+  /// Equal values and proofs in two groups with equally wide elements do not share entries.
+  #[cfg(feature = "nonmembership-cache")]
+  #[test]
+  fn test_verify_nonmembership_cache_binds_group() {
+    fn proof<G: UnknownOrderGroup>() -> NonmembershipProof<G, &'static str> {
+      let x = G::unknown_order_elem();
+      let poe_bytes = G::to_bytes(&x);
+      let mut poke2_bytes = [poe_bytes.clone(), poe_bytes.clone()].concat();
+      poke2_bytes.extend(vec![1; 32]);
+      NonmembershipProof {
+        phantom: PhantomData,
+        d: x.clone(),
+        v: x.clone(),
+        gv_inv: x,
+        poke2_proof: Poke2::from_bytes(&poke2_bytes).unwrap(),
+        poe_proof: Poe::from_bytes(&poe_bytes).unwrap(),
+      }
+    }
+    let acc = Accumulator::<DummyRsa, &'static str>::empty();
+    let acc_2 = Accumulator::<DummyRsa2, &'static str>::empty();
+    assert!(acc.digest() == acc_2.digest());
+    NONMEMBERSHIP_CACHE.with(|cache| *cache.borrow_mut() = NonmembershipCache::default());
+    assert!(!acc.verify_nonmembership(&["c"], &proof()));
+    NONMEMBERSHIP_CACHE.with(|cache| {
+      cache.borrow_mut().results.values_mut().next().unwrap().0 = true;
+    });
+    assert!(acc.verify_nonmembership(&["c"], &proof()));
+    assert!(!acc_2.verify_nonmembership(&["c"], &proof()));
+    NONMEMBERSHIP_CACHE.with(|cache| assert_eq!(cache.borrow().results.len(), 2));
+  }
+
+  #[cfg(feature = "nonmembership-cache")]
+  #[test]
+  fn test_nonmembership_cache_eviction() {
+    let key = |i: usize| ([0; 32], hash(&Blake2b::default, &i));
+    let mut cache = NonmembershipCache::default();
+    for i in 0..NONMEMBERSHIP_CACHE_CAPACITY {
+      cache.insert(key(i), true);
+    }
+    assert_eq!(cache.get(&key(0)), Some(true));
+    cache.insert(key(NONMEMBERSHIP_CACHE_CAPACITY), false);
+    assert_eq!(cache.results.len(), NONMEMBERSHIP_CACHE_CAPACITY);
+    // Entry 1 was the least recently used after entry 0 was read.
+    assert_eq!(cache.get(&key(1)), None);
+    assert_eq!(cache.get(&key(0)), Some(true));
+    assert_eq!(cache.get(&key(NONMEMBERSHIP_CACHE_CAPACITY)), Some(false));
+  }
+
+  test_all_groups!(
+    test_prove_nonmembership_degenerate,
+    test_prove_nonmembership_degenerate_rsa2048,
+    test_prove_nonmembership_degenerate_class,
+  );
+  fn test_prove_nonmembership_degenerate<G: UnknownOrderGroup>() {
+    // No elements: rejected, since the empty product divides every set product.
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<G, &'static str>(&acc_set);
+    match acc.prove_nonmembership(&acc_set, &[]) {
+      Err(AccError::EmptyElems) => {}
+      _ => panic!("expected EmptyElems"),
+    }
+
+    // Empty set: every element is a non-member, with the trivial witness `d = 1`, `v = acc`.
+    let empty = Accumulator::<G, &'static str>::empty();
+    let proof = empty.prove_nonmembership(&[], &["a", "b"]).unwrap();
+    assert!(proof.d == G::id());
+    assert!(proof.v == empty.value);
+    assert!(empty.verify_nonmembership(&["a", "b"], &proof));
+    // The trivial proof does not depend on the elements, and indeed holds for any of them.
+    assert!(empty.verify_nonmembership(&["c"], &proof));
+    assert!(!acc.verify_nonmembership(&["c"], &proof));
+  }
+
+  test_all_groups!(
+    test_prove_nonmembership_with_product,
+    test_prove_nonmembership_with_product_rsa2048,
+    test_prove_nonmembership_with_product_class,
+  );
+  fn test_prove_nonmembership_with_product<G: UnknownOrderGroup>() {
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<G, &'static str>(&acc_set);
+    let non_members = ["c", "d"];
+    let proof = acc
+      .prove_nonmembership(&acc_set, &non_members)
+      .expect("valid proof expected");
+    let proof_with_product = acc
+      .prove_nonmembership_with_product(&prime_hash_product(&acc_set), &non_members)
+      .expect("valid proof expected");
+    assert!(acc.verify_nonmembership(&non_members, &proof));
+    assert!(acc.verify_nonmembership(&non_members, &proof_with_product));
+    assert!(proof == proof_with_product);
+  }
+
+  test_all_groups!(
+    test_nonmembership_witness_from_product,
+    test_nonmembership_witness_from_product_rsa2048,
+    test_nonmembership_witness_from_product_class,
+  );
+  fn test_nonmembership_witness_from_product<G: UnknownOrderGroup>() {
+    let acc_set = ["a", "b"];
+    let acc = new_acc::<G, &'static str>(&acc_set);
+    let set_product = prime_hash_product(&acc_set);
+    let (d, v) = acc
+      .nonmembership_witness_from_product(&set_product, &["c"])
+      .expect("valid witness expected");
+    assert!(G::op(&G::exp(&d, &hash_to_prime(&"c")), &v) == G::unknown_order_elem());
+
+    let proof = acc
+      .prove_nonmembership_with_product(&set_product, &["c"])
+      .expect("valid proof expected");
+    assert!(proof.d == d && proof.v == v);
+    assert!(acc.verify_nonmembership(&["c"], &proof));
+
+    assert!(acc
+      .nonmembership_witness_from_product(&set_product, &["a"])
+      .is_err());
+  }
+
   test_all_groups!(
     test_compute_sub_witness,
     test_compute_sub_witness_rsa2048,
@@ -559,6 +2264,39 @@ mod tests {
     test_compute_individual_witnesses::<Rsa2048>();
   }
 
+  #[test]
+  fn test_compute_individual_witnesses_cached() {
+    let elems = ["b", "c", "d", "e", "f"];
+    let witness_multiple = Witness(new_acc::<Rsa2048, &'static str>(&["a"]));
+    assert!(
+      witness_multiple.compute_individual_witnesses_cached(&elems)
+        == witness_multiple.compute_individual_witnesses(&elems)
+    );
+  }
+
+  #[test]
+  fn test_from_iter() {
+    let acc = [5, 7, 11]
+      .iter()
+      .map(|&p| int(p))
+      .collect::<Accumulator<Rsa2048, &'static str>>();
+    let g = Rsa2048::unknown_order_elem();
+    assert!(acc.value == Rsa2048::exp(&g, &int(385)));
+    let witness = [int(5), int(11)]
+      .iter()
+      .cloned()
+      .collect::<Accumulator<Rsa2048, &'static str>>();
+    let proof = MembershipProof {
+      proof: Poe::prove(&witness.value, &int(7), &acc.value),
+      witness: Witness(witness),
+    };
+    assert!(acc.verify_membership_prime(&int(7), &proof));
+    assert!(!acc.verify_membership_prime(&int(13), &proof));
+    assert!(
+      std::iter::empty().collect::<Accumulator<Rsa2048, &'static str>>() == Accumulator::empty()
+    );
+  }
+
   #[test]
   fn test_from() {
     let acc = new_acc::<Rsa2048, &'static str>(&["a", "b", "c"]);
@@ -0,0 +1,140 @@
+//! Read-only accumulator snapshot that caches a membership witness for each of its elements, for
+//! servers answering many membership queries against a fixed state.
+use crate::accumulator::{AccError, Accumulator, MembershipProof, Witness};
+use crate::group::UnknownOrderGroup;
+use crate::hash::hash_to_prime;
+use crate::proof::Poe;
+use rug::Integer;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug)]
+/// An accumulator that can no longer be updated, together with the prime and witness of each
+/// element it was frozen with. Built by `Accumulator::freeze`.
+///
+/// There are no methods to add or delete elements, and the accumulator is only lent out by shared
+/// reference, so the cached witnesses cannot go stale. Call `thaw` to get the accumulator back.
+pub struct FrozenAccumulator<G: UnknownOrderGroup, T: Hash> {
+  acc: Accumulator<G, T>,
+  witnesses: HashMap<T, (Integer, Witness<G, T>)>,
+}
+
+impl<G: UnknownOrderGroup, T: Clone + Eq + Hash> FrozenAccumulator<G, T> {
+  /// Freezes `acc`, computing the witness of each element of `elems` from `elems_witness`, a
+  /// witness for all of them together, in O(N log N) group operations via `root_factor`.
+  ///
+  /// Returns `BadWitness` if `elems_witness` is not a witness for `elems`. Repeated elements are
+  /// cached once.
+  pub fn new(
+    acc: Accumulator<G, T>,
+    elems_witness: &Witness<G, T>,
+    elems: &[T],
+  ) -> Result<Self, AccError> {
+    let primes = elems.iter().map(hash_to_prime).collect::<Vec<_>>();
+    let product: Integer = primes.iter().product();
+    if G::exp(&elems_witness.0.value, &product) != acc.value {
+      return Err(AccError::BadWitness);
+    }
+    let witnesses = if elems.is_empty() {
+      HashMap::new()
+    } else {
+      let witnesses = elems_witness.root_factor(&primes);
+      elems
+        .iter()
+        .cloned()
+        .zip(primes.into_iter().zip(witnesses))
+        .collect()
+    };
+    Ok(Self { acc, witnesses })
+  }
+
+  /// Returns the frozen accumulator.
+  pub fn accumulator(&self) -> &Accumulator<G, T> {
+    &self.acc
+  }
+
+  /// Returns the number of cached elements.
+  pub fn len(&self) -> usize {
+    self.witnesses.len()
+  }
+
+  /// Returns whether no elements are cached.
+  pub fn is_empty(&self) -> bool {
+    self.witnesses.is_empty()
+  }
+
+  /// Returns whether a witness for `elem` is cached.
+  pub fn contains(&self, elem: &T) -> bool {
+    self.witnesses.contains_key(elem)
+  }
+
+  /// Returns the cached witness for `elem`, if any.
+  pub fn witness(&self, elem: &T) -> Option<&Witness<G, T>> {
+    self.witnesses.get(elem).map(|(_, witness)| witness)
+  }
+
+  /// Computes a membership proof for `elem` from its cached witness, at the cost of one PoE. The
+  /// proof verifies with `Accumulator::verify_membership` against `accumulator()`.
+  ///
+  /// Returns `NotPresent` if `elem` was not among the elements the accumulator was frozen with.
+  pub fn membership_proof(&self, elem: &T) -> Result<MembershipProof<G, T>, AccError> {
+    let (prime, witness) = self.witnesses.get(elem).ok_or(AccError::NotPresent)?;
+    let proof = Poe::<G>::prove(&witness.0.value, prime, &self.acc.value);
+    Ok(MembershipProof {
+      witness: witness.clone(),
+      proof,
+    })
+  }
+
+  /// Returns the accumulator, discarding the cached witnesses.
+  pub fn thaw(self) -> Accumulator<G, T> {
+    self.acc
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::Rsa2048;
+
+  test_all_groups!(test_freeze, test_freeze_rsa2048, test_freeze_class,);
+  fn test_freeze<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d", "e"];
+    let acc = Accumulator::<G, &'static str>::empty().add(&elems);
+    let frozen = acc
+      .clone()
+      .freeze(&Witness(Accumulator::empty()), &elems)
+      .unwrap();
+    assert_eq!(frozen.len(), 5);
+    for elem in &elems {
+      assert!(frozen.contains(elem));
+      let proof = frozen.membership_proof(elem).unwrap();
+      assert!(frozen.accumulator().verify_membership(elem, &proof));
+      assert!(*frozen.witness(elem).unwrap() == proof.witness);
+    }
+    assert!(!frozen.contains(&"f"));
+    match frozen.membership_proof(&"f") {
+      Err(AccError::NotPresent) => {}
+      _ => panic!("expected NotPresent for an element that was not frozen"),
+    }
+    assert!(frozen.thaw() == acc);
+  }
+
+  #[test]
+  fn test_freeze_bad_witness() {
+    let acc = Accumulator::<Rsa2048, &'static str>::empty().add(&["a", "b"]);
+    assert!(acc
+      .clone()
+      .freeze(&Witness(Accumulator::empty()), &["a"])
+      .is_err());
+    let frozen = acc
+      .freeze(&Witness(Accumulator::empty().add(&["a"])), &["b"])
+      .unwrap();
+    assert!(frozen.contains(&"b"));
+    assert!(!frozen.contains(&"a"));
+    assert!(Accumulator::<Rsa2048, &'static str>::empty()
+      .freeze(&Witness(Accumulator::empty()), &[])
+      .unwrap()
+      .is_empty());
+  }
+}
@@ -2,9 +2,11 @@
 //!
 //! Using a class group instead of an RSA group for accumulators or vector commitments eliminates
 //! the need for a trusted setup, albeit at the expense of slower operations.
-use super::{ElemFrom, Group, UnknownOrderGroup};
+use super::{ElemFrom, Group, GroupDescriptor, UnknownOrderGroup};
+use crate::hash::hash_to_prime;
 use crate::util;
 use crate::util::{int, TypeRep};
+use rug::integer::Order;
 use rug::{Assign, Integer};
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
@@ -31,6 +33,20 @@ const DISCRIMINANT2048_DECIMAL: &str =
 lazy_static! {
   pub static ref CLASS_GROUP_DISCRIMINANT: Integer =
     Integer::from_str(DISCRIMINANT2048_DECIMAL).unwrap();
+
+  // `|d| ^ (1/4)`, the bound at which `square_nudupl` stops its partial Euclidean reduction.
+  static ref NUDUPL_BOUND: Integer = int(CLASS_GROUP_DISCRIMINANT.abs_ref()).root(4);
+
+  // Returned by `unknown_order_elem`, so that it costs a clone rather than a division.
+  static ref UNKNOWN_ORDER_ELEM: ClassElem = {
+    // a = 2
+    // b = 1
+    // c = (b * b - d) / 4a
+    let a = int(2);
+    let b = int(1);
+    let c = int(1 - &*CLASS_GROUP_DISCRIMINANT) / int(8);
+    ClassElem { a, b, c }
+  };
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -78,6 +94,8 @@ impl ClassGroup {
   }
 
   #[allow(non_snake_case)]
+  /// Squares `x` by composition. `Group::square` uses the faster `square_nudupl` instead.
+  ///
   /// This method is only public for benchmarking. You should not need to use it.
   pub fn square(x: &ClassElem) -> ClassElem {
     // Solve `bk = c mod a` for `k`, represented by `mu`, `v` and any integer `n` s.t.
@@ -96,6 +114,86 @@ impl ClassGroup {
     Self::elem((a, b, c))
   }
 
+  #[allow(non_snake_case)]
+  /// This method is only public for benchmarking. You should not need to use it.
+  // Squares `x` with Shanks' NUDUPL, which runs the extended Euclidean algorithm only until the
+  // remainders drop below `|d| ^ (1/4)`, so that the unreduced square has coefficients about the
+  // size of `|d| ^ (1/2)` instead of `|d|` as in `square`, and takes only a step or two to reduce.
+  // Ported from Chia's implementation: https://github.com/Chia-Network/chiavdf.
+  pub fn square_nudupl(x: &ClassElem) -> ClassElem {
+    let L = &*NUDUPL_BOUND;
+
+    // G = gcd(b, a) = y * b + _ * a
+    // By = a / G
+    // Dy = b / G
+    // bx = y * c mod By
+    let (G, y, _) = <(Integer, Integer, Integer)>::from(x.b.gcd_cofactors_ref(&x.a));
+    let By = int(x.a.div_exact_ref(&G));
+    let Dy = int(x.b.div_exact_ref(&G));
+    let (_, bx) = int(&y * &x.c).div_rem_euc(int(&By));
+
+    if By <= *L {
+      // dx = (bx * Dy - c) / By
+      // A = By^2
+      // B = b - (bx + By)^2 + A + C
+      // C = bx^2 - G * dx
+      let dx = (int(&bx * &Dy) - &x.c).div_exact(&By);
+      let a = int(By.square_ref());
+      let c = int(bx.square_ref());
+      let b = &x.b - int(&bx + &By).square() + &a + &c;
+      let c = c - G * dx;
+      return Self::elem((a, b, c));
+    }
+
+    // Partial extended Euclid on (By, bx), tracking one cofactor, until the remainder `bx` drops
+    // below `L`.
+    let (mut y, mut x_co, mut by, mut bx) = (int(0), int(-1), By.clone(), bx);
+    while bx != 0 && bx > *L {
+      let (q, r) = by.div_rem_floor(bx.clone());
+      y -= q * &x_co;
+      by = r;
+      std::mem::swap(&mut by, &mut bx);
+      std::mem::swap(&mut y, &mut x_co);
+    }
+    x_co = -x_co;
+    if x_co > 0 {
+      y = -y;
+    } else {
+      by = -by;
+    }
+
+    // ax = G * x
+    // ay = G * y
+    // dx = (Dy * bx - c * x) / By
+    // Q1 = y * dx
+    // dy = (Q1 + Dy) / x
+    // B = G * (2 * Q1 + Dy)
+    let ax = int(&G * &x_co);
+    let ay = int(&G * &y);
+    let dx = (int(&Dy * &bx) - int(&x.c * &x_co)).div_exact(&By);
+    let Q1 = int(&y * &dx);
+    let dy = int(&Q1 + &Dy);
+    let b = int(&dy + &Q1) * &G;
+    let dy = dy.div_exact(&x_co);
+
+    // A = by^2 - ay * dy
+    // B = B - (bx + by)^2 + by^2 + bx^2
+    // C = bx^2 - ax * dx
+    let by_sq = int(by.square_ref());
+    let bx_sq = int(bx.square_ref());
+    let b = b - int(&bx + &by).square() + &by_sq + &bx_sq;
+    let a = by_sq - ay * dy;
+    let c = bx_sq - ax * dx;
+    Self::elem((a, b, c))
+  }
+
+  /// Returns the number of bytes needed to encode the `a` or `|b|` coefficient of a reduced form
+  /// with discriminant `d`. Reduced forms satisfy `|b| <= a <= sqrt(|d| / 3)`, so half the bit
+  /// length of `d` (rounded up) always suffices.
+  fn coefficient_bytes(d: &Integer) -> usize {
+    (d.significant_bits() as usize).div_ceil(2).div_ceil(8)
+  }
+
   fn discriminant(a: &Integer, b: &Integer, c: &Integer) -> Integer {
     int(b.square_ref()) - int(4) * a * c
   }
@@ -123,8 +221,23 @@ impl TypeRep for ClassGroup {
 impl Group for ClassGroup {
   type Elem = ClassElem;
 
+  // `a`, the sign of `b` and `|b|`, with 128-byte coefficients for the 2048-bit discriminant.
+  const ELEM_BYTES: usize = 2 * 128 + 1;
+
   #[allow(non_snake_case)]
+  fn square_(_: &Integer, x: &ClassElem) -> ClassElem {
+    Self::square_nudupl(x)
+  }
+
   fn op_(_: &Integer, x: &ClassElem, y: &ClassElem) -> ClassElem {
+    // Identity short-circuit. The identity is the only reduced form with `a = 1`.
+    if x.a == 1 {
+      return y.clone();
+    }
+    if y.a == 1 {
+      return x.clone();
+    }
+
     // g = (b1 + b2) / 2
     // h = (b2 - b1) / 2
     // w = gcd(a1, a2, g)
@@ -210,21 +323,90 @@ impl Group for ClassGroup {
       if n.is_odd() {
         val = Self::op(&val, &a);
       }
-      a = Self::square(&a);
+      a = Self::square_nudupl(&a);
       n >>= 1;
     }
   }
+
+  // Encodes `a`, the sign of `b` and `|b|`. The coefficient `c` is omitted since it is determined
+  // by the discriminant.
+  fn to_bytes_(d: &Integer, x: &ClassElem) -> Vec<u8> {
+    let len = Self::coefficient_bytes(d);
+    let mut bytes = util::to_fixed_be_bytes(&x.a, len);
+    bytes.push((x.b < 0).into());
+    bytes.extend(util::to_fixed_be_bytes(&x.b, len));
+    bytes
+  }
+
+  // Recovers `c` from `a`, `b` and the discriminant, then reduces the form.
+  fn from_bytes_(d: &Integer, bytes: &[u8]) -> Option<ClassElem> {
+    let len = Self::coefficient_bytes(d);
+    if bytes.len() != 2 * len + 1 || bytes[len] > 1 {
+      return None;
+    }
+    let a = Integer::from_digits(&bytes[..len], Order::Msf);
+    let mut b = Integer::from_digits(&bytes[len + 1..], Order::Msf);
+    if bytes[len] == 1 {
+      b = -b;
+    }
+    if a <= 0 {
+      return None;
+    }
+
+    // c = (b * b - d) / 4a
+    let (c, rem) = (int(b.square_ref()) - d).div_rem(int(4 * &a));
+    if rem != 0 {
+      return None;
+    }
+    // `from_bytes` reduces the form.
+    Some(ClassElem { a, b, c })
+  }
+
+  fn descriptor_(d: &Integer) -> GroupDescriptor {
+    GroupDescriptor {
+      family: "class",
+      params: d.to_digits(Order::Msf),
+    }
+  }
+
+  // The discriminant is prime, so every form lies in the principal genus, and the class number is
+  // odd, so every class is a square. What remains to check is that the form is valid.
+  fn in_subgroup_(d: &Integer, x: &ClassElem) -> bool {
+    Self::discriminant(&x.a, &x.b, &x.c) == *d
+      && Self::is_reduced(&x.a, &x.b, &x.c)
+      && int(x.a.gcd_ref(&x.b)).gcd(&x.c) == 1
+  }
+
+  fn normalize_elem_(_: &Integer, x: &mut ClassElem) {
+    let (a, b, c) = Self::reduce(x.a.clone(), x.b.clone(), x.c.clone());
+    *x = ClassElem { a, b, c };
+  }
 }
 
 impl UnknownOrderGroup for ClassGroup {
-  fn unknown_order_elem_(d: &Integer) -> ClassElem {
-    // a = 2
-    // b = 1
-    // c = (b * b - d) / 4a
-    let a = int(2);
-    let b = int(1);
-    let c = int(1 - d) / int(8);
-    ClassElem { a, b, c }
+  fn unknown_order_elem_(_: &Integer) -> ClassElem {
+    UNKNOWN_ORDER_ELEM.clone()
+  }
+
+  // Hashes `seed` to a prime `a = 3 mod 4` for which `d` is a square mod `a`, so that the square
+  // root `b = d^((a + 1) / 4) mod a` exists. Taking `b` odd makes `b^2 = d mod 4a` as
+  // `d = 1 mod 4`, so `(a, b, (b^2 - d) / 4a)` is a form of discriminant `d`.
+  fn generator_(d: &Integer, seed: &[u8]) -> ClassElem {
+    let mut counter = 0_u64;
+    loop {
+      let a = hash_to_prime(&(seed, counter));
+      counter += 1;
+      if a.mod_u(4) != 3 || d.legendre(&a) != 1 {
+        continue;
+      }
+      let exp = int(&a + 1) >> 2;
+      let mut b = d.pow_mod_ref(&exp, &a).map(int).unwrap();
+      if b.is_even() {
+        b = int(&a - &b);
+      }
+      let c = (int(b.square_ref()) - d) / int(4 * &a);
+      return Self::elem((a, b, c));
+    }
   }
 }
 
@@ -643,7 +825,7 @@ mod tests {
       let mut curr_prod = ClassGroup::id();
       for elem in &gs {
         if elem != g_elem {
-          curr_prod = ClassGroup::op(&curr_prod, &elem);
+          curr_prod = ClassGroup::op(&curr_prod, elem);
           assert!(ClassGroup::validate(
             &curr_prod.a,
             &curr_prod.b,
@@ -651,8 +833,8 @@ mod tests {
           ));
         }
       }
-      assert_eq!(ClassGroup::id(), ClassGroup::op(&g_inv, &g_elem));
-      assert_eq!(curr_prod, ClassGroup::op(&g_inv, &g_star));
+      assert_eq!(ClassGroup::id(), ClassGroup::op(g_inv, g_elem));
+      assert_eq!(curr_prod, ClassGroup::op(g_inv, &g_star));
     }
   }
 
@@ -704,6 +886,137 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_to_bytes() {
+    let g = ClassGroup::unknown_order_elem();
+    let g_inv = ClassGroup::inv(&g);
+    let bytes = ClassGroup::to_bytes(&g);
+    assert_eq!(bytes.len(), ClassGroup::to_bytes(&ClassGroup::id()).len());
+    assert_ne!(bytes, ClassGroup::to_bytes(&g_inv));
+    let g_squared = ClassGroup::op(&g, &g);
+    assert_eq!(
+      ClassGroup::to_bytes(&g_squared),
+      ClassGroup::to_bytes(&ClassGroup::square(&g))
+    );
+  }
+
+  #[test]
+  fn test_from_bytes() {
+    let g = ClassGroup::exp(&ClassGroup::unknown_order_elem(), &int(1000));
+    let bytes = ClassGroup::to_bytes(&g);
+    assert_eq!(ClassGroup::from_bytes(&bytes), Some(g));
+    assert_eq!(
+      ClassGroup::from_bytes(&ClassGroup::to_bytes(&ClassGroup::id())),
+      Some(ClassGroup::id())
+    );
+    assert_eq!(ClassGroup::from_bytes(&bytes[1..]), None);
+    assert_eq!(ClassGroup::from_bytes(&vec![0; bytes.len()]), None);
+  }
+
+  #[test]
+  fn test_from_bytes_unreduced() {
+    let g = ClassGroup::exp(&ClassGroup::unknown_order_elem(), &int(1000));
+    // The equivalent form `(a, b + 2a, a + b + c)` is not reduced, since `|b + 2a| > a`.
+    let b = int(&g.b + int(2 * &g.a));
+    assert!(*b.as_abs() > g.a);
+    let len = ClassGroup::coefficient_bytes(ClassGroup::rep());
+    let mut bytes = util::to_fixed_be_bytes(&g.a, len);
+    bytes.push(if b < 0 { 1 } else { 0 });
+    bytes.extend(util::to_fixed_be_bytes(&b, len));
+    let loaded = ClassGroup::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, g);
+    assert_eq!(ClassGroup::to_bytes(&loaded), ClassGroup::to_bytes(&g));
+    let hash = |x: &ClassElem| {
+      let mut hasher = DefaultHasher::new();
+      x.hash(&mut hasher);
+      hasher.finish()
+    };
+    assert_eq!(hash(&loaded), hash(&g));
+  }
+
+  #[test]
+  fn test_batch_normalize() {
+    let reduced = (1..5)
+      .map(|i| ClassGroup::exp(&ClassGroup::unknown_order_elem(), &int(1000 * i)))
+      .collect::<Vec<_>>();
+    // Equivalent but unreduced forms, via `(a, b, c) -> (c, -b, a)` and
+    // `(a, b, c) -> (a, b + 2ka, ak^2 + bk + c)`.
+    let mut batch = reduced
+      .iter()
+      .enumerate()
+      .map(|(k, x)| {
+        let (a, b, c) = (x.c.clone(), -x.b.clone(), x.a.clone());
+        let k = int(k + 1);
+        let new_b = int(&b + int(2 * &k) * &a);
+        let new_c = int(&a * int(k.square_ref())) + int(&b * &k) + c;
+        ClassElem {
+          a,
+          b: new_b,
+          c: new_c,
+        }
+      })
+      .collect::<Vec<_>>();
+    assert!(batch.iter().zip(&reduced).all(|(x, y)| x.b != y.b));
+    let mut singles = batch.clone();
+    ClassGroup::batch_normalize(&mut batch);
+    for x in &mut singles {
+      ClassGroup::normalize_elem(x);
+    }
+    assert!(batch == reduced);
+    assert!(singles == reduced);
+  }
+
+  #[test]
+  fn test_in_subgroup() {
+    let g = ClassGroup::unknown_order_elem();
+    assert!(ClassGroup::in_subgroup(&g));
+    assert!(ClassGroup::in_subgroup(&ClassGroup::id()));
+    assert!(ClassGroup::in_subgroup(&ClassGroup::exp(
+      &g,
+      &int(1_000_003)
+    )));
+    assert!(ClassGroup::in_subgroup(&ClassGroup::generator(b"seed")));
+
+    // Right discriminant, but not reduced.
+    let g_unreduced = ClassElem {
+      a: int(&g.a),
+      b: &g.b + int(2 * &g.a),
+      c: int(&g.c + &g.a) + &g.b,
+    };
+    assert!(
+      ClassGroup::discriminant(&g_unreduced.a, &g_unreduced.b, &g_unreduced.c)
+        == *ClassGroup::rep()
+    );
+    assert!(!ClassGroup::in_subgroup(&g_unreduced));
+    // Wrong discriminant.
+    let g_wrong_c = ClassElem {
+      a: int(&g.a),
+      b: int(&g.b),
+      c: int(&g.c + 1),
+    };
+    assert!(!ClassGroup::in_subgroup(&g_wrong_c));
+  }
+
+  #[test]
+  fn test_square_nudupl() {
+    let g = ClassGroup::unknown_order_elem();
+    let mut x = ClassGroup::id();
+    for i in 0..200_u64 {
+      let squared = ClassGroup::square_nudupl(&x);
+      assert_eq!(squared, ClassGroup::op(&x, &x));
+      assert_eq!(squared, ClassGroup::square(&x));
+      // Starts from forms with small coefficients, which take the early exit, and quickly moves
+      // on to full-size ones.
+      let n = crate::hash::blake2b(&i).keep_bits(16);
+      x = ClassGroup::op(&ClassGroup::op(&x, &x), &ClassGroup::exp(&g, &n));
+    }
+    let g_inv = ClassGroup::inv(&g);
+    assert_eq!(
+      ClassGroup::square_nudupl(&g_inv),
+      ClassGroup::op(&g_inv, &g_inv)
+    );
+  }
+
   #[test]
   fn test_square_basic() {
     let g = ClassGroup::unknown_order_elem();
@@ -0,0 +1,271 @@
+//! Tiny RSA groups for tests. Their moduli are trivially factorable, so they are useless for
+//! anything but exercising generic code over more than one `UnknownOrderGroup` cheaply.
+use super::{hash_to_qr, ElemFrom, Group, UnknownOrderGroup};
+use crate::util::{int, random_bits, TypeRep};
+use rand_core::RngCore;
+use rug::integer::IsPrime;
+use rug::Integer;
+use std::cell::Cell;
+
+// Defines an RSA group `$group` with elements `$elem` over `$modulus`, which should be a product of
+// two safe primes. As in `Rsa2048`, `x` and `-x` are treated as the same element.
+macro_rules! dummy_rsa_group {
+  ($group:ident, $elem:ident, $modulus:ident, $modulus_value:expr, $elem_bytes:expr, $doc:expr) => {
+    lazy_static! {
+      static ref $modulus: Integer = int($modulus_value);
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    #[doc = $doc]
+    pub enum $group {}
+
+    impl TypeRep for $group {
+      type Rep = Integer;
+      fn rep() -> &'static Self::Rep {
+        &$modulus
+      }
+    }
+
+    dummy_rsa_group_impls!($group, $elem, $elem_bytes);
+  };
+}
+
+// Implements the element type and the group traits for `$group`, given its `TypeRep` impl.
+macro_rules! dummy_rsa_group_impls {
+  ($group:ident, $elem:ident, $elem_bytes:expr) => {
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    /// An element of a dummy RSA group.
+    pub struct $elem(pub Integer);
+
+    impl $group {
+      /// Returns the modulus of the group.
+      pub fn modulus() -> &'static Integer {
+        Self::rep()
+      }
+    }
+
+    rsa_group_impls!($group, $elem, $elem_bytes);
+
+    /// Reduces `t` into `[0, N)` (so negative inputs are fine) before picking the smaller of `x`
+    /// and `N - x`. Unlike for `Rsa2048`, a `u64` can exceed the tiny modulus.
+    impl<T> ElemFrom<T> for $group
+    where
+      Integer: From<T>,
+    {
+      fn elem(t: T) -> $elem {
+        let modulus = Self::rep();
+        let mut val = Integer::from(t) % modulus;
+        if val < 0 {
+          val += modulus;
+        }
+        // Explicit types, since `Integer: From<T>` shadows the other `From` impls here.
+        let doubled: Integer = val.clone() << 1;
+        if doubled > *modulus {
+          $elem(modulus - val)
+        } else {
+          $elem(val)
+        }
+      }
+    }
+
+    impl UnknownOrderGroup for $group {
+      // A quadratic residue, as in `Rsa2048`.
+      fn unknown_order_elem_(_: &Integer) -> $elem {
+        Self::elem(4)
+      }
+
+      fn generator_(modulus: &Integer, seed: &[u8]) -> $elem {
+        Self::elem(hash_to_qr(modulus, seed))
+      }
+    }
+  };
+}
+
+// 1019 * 1187, where 1019 = 2 * 509 + 1 and 1187 = 2 * 593 + 1.
+dummy_rsa_group!(
+  DummyRsa,
+  DummyRsaElem,
+  DUMMY_RSA_MODULUS,
+  1_209_553,
+  3,
+  "Tiny RSA group with modulus `1019 * 1187`. For tests only."
+);
+
+// 2027 * 2039, where 2027 = 2 * 1013 + 1 and 2039 = 2 * 1019 + 1.
+dummy_rsa_group!(
+  DummyRsa2,
+  DummyRsa2Elem,
+  DUMMY_RSA2_MODULUS,
+  4_133_053,
+  3,
+  "Tiny RSA group with modulus `2027 * 2039`. For tests only."
+);
+
+thread_local! {
+  static DUMMY_RSA_VAR_MODULUS: Cell<&'static Integer> = Cell::new(&DUMMY_RSA_MODULUS);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Tiny RSA group whose modulus is chosen at runtime with `with_modulus`, for running the same
+/// invariants over many moduli. Defaults to the modulus of `DummyRsa`. For tests only.
+pub enum DummyRsaVar {}
+
+impl DummyRsaVar {
+  /// Runs `f` with the group modulus set to `modulus` on the current thread, restoring the previous
+  /// modulus afterwards. `modulus` should be a product of two safe primes below `2^64`.
+  ///
+  /// Each call leaks its modulus, since `TypeRep::rep` hands out `'static` references.
+  pub fn with_modulus<R>(modulus: Integer, f: impl FnOnce() -> R) -> R {
+    let modulus: &'static Integer = Box::leak(Box::new(modulus));
+    let prev = DUMMY_RSA_VAR_MODULUS.with(|m| m.replace(modulus));
+    let res = f();
+    DUMMY_RSA_VAR_MODULUS.with(|m| m.set(prev));
+    res
+  }
+
+  /// Samples a product of two distinct safe primes of `bits` bits each from `rng`, for use with
+  /// `with_modulus`.
+  ///
+  /// Panics unless `6 <= bits <= 32`. Below 6 bits there is at most one safe prime of each length
+  /// (7, 11 and 23 for 3, 4 and 5 bits), so no two distinct ones can be drawn.
+  pub fn random_modulus(rng: &mut impl RngCore, bits: u32) -> Integer {
+    assert!(
+      (6..=32).contains(&bits),
+      "random_modulus needs 6 to 32 bits, got {}",
+      bits
+    );
+    let mut safe_prime = || loop {
+      let q = (random_bits(rng, bits - 1) | (int(1) << (bits - 2))).next_prime();
+      let p: Integer = (q << 1) + 1;
+      if p.significant_bits() == bits && p.is_probably_prime(30) != IsPrime::No {
+        return p;
+      }
+    };
+    let p = safe_prime();
+    loop {
+      let q = safe_prime();
+      if q != p {
+        return p * q;
+      }
+    }
+  }
+}
+
+impl TypeRep for DummyRsaVar {
+  type Rep = Integer;
+  fn rep() -> &'static Self::Rep {
+    DUMMY_RSA_VAR_MODULUS.with(Cell::get)
+  }
+}
+
+dummy_rsa_group_impls!(DummyRsaVar, DummyRsaVarElem, 8);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::{square_and_multiply, test_rng};
+
+  #[test]
+  fn test_elem() {
+    assert!(DummyRsa::elem(-2) == DummyRsa::elem(2));
+    assert!(DummyRsa::elem(int(DummyRsa::modulus() + 2)) == DummyRsa::elem(2));
+    assert!(DummyRsa2::elem(-2) == DummyRsa2::elem(2));
+    let modulus = DummyRsa::modulus().to_u64().unwrap();
+    assert!(DummyRsa::elem(modulus + 5) == DummyRsa::elem(5));
+    assert!(DummyRsa::elem(modulus * 1000 + 5) == DummyRsa::elem(5));
+    assert!(DummyRsa::elem(u64::MAX).0 < *DummyRsa::modulus());
+  }
+
+  #[test]
+  fn test_group_laws() {
+    let x = DummyRsa::elem(12345);
+    assert!(DummyRsa::op(&x, &DummyRsa::inv(&x)) == DummyRsa::id());
+    assert!(DummyRsa::exp(&x, &int(3)) == DummyRsa::op(&x, &DummyRsa::op(&x, &x)));
+    assert!(DummyRsa::descriptor() != DummyRsa2::descriptor());
+  }
+
+  // 23 * 47, where 23 = 2 * 11 + 1 and 47 = 2 * 23 + 1.
+  dummy_rsa_group!(
+    DummyRsa3,
+    DummyRsa3Elem,
+    DUMMY_RSA3_MODULUS,
+    1081,
+    2,
+    "Tiny RSA group declared only for `test_declared_group_laws`."
+  );
+
+  #[test]
+  fn test_declared_group_laws() {
+    let g = DummyRsa3::unknown_order_elem();
+    let elems = (1..200_u32)
+      .map(DummyRsa3::elem)
+      .filter(|x| int(x.0.gcd_ref(DummyRsa3::modulus())) == 1)
+      .collect::<Vec<_>>();
+    for (x, y) in elems.iter().zip(elems.iter().skip(1)) {
+      assert!(DummyRsa3::op(x, &DummyRsa3::id()) == *x);
+      assert!(DummyRsa3::op(x, &DummyRsa3::inv(x)) == DummyRsa3::id());
+      assert!(DummyRsa3::op(x, y) == DummyRsa3::op(y, x));
+      assert!(DummyRsa3::op(&DummyRsa3::op(x, y), &g) == DummyRsa3::op(x, &DummyRsa3::op(y, &g)));
+      assert!(DummyRsa3::exp(x, &int(3)) == DummyRsa3::op(x, &DummyRsa3::op(x, x)));
+      assert!(DummyRsa3::exp(x, &int(-2)) == DummyRsa3::inv(&DummyRsa3::op(x, x)));
+      assert!(DummyRsa3::from_bytes(&DummyRsa3::to_bytes(x)).as_ref() == Some(x));
+    }
+    assert!(DummyRsa3::descriptor() != DummyRsa::descriptor());
+  }
+
+  #[test]
+  fn test_exp_reference() {
+    let mut rng = test_rng(0);
+    for i in 0..1000 {
+      let (x, n) = (random_bits(&mut rng, 32), random_bits(&mut rng, 64));
+      let expected = DummyRsa::elem(square_and_multiply(&x, &n, DummyRsa::modulus()));
+      assert!(DummyRsa::exp(&DummyRsa::elem(x.clone()), &n) == expected);
+      let expected_2 = DummyRsa2::elem(square_and_multiply(&x, &n, DummyRsa2::modulus()));
+      assert!(DummyRsa2::exp(&DummyRsa2::elem(x.clone()), &n) == expected_2);
+      if i % 4 == 0 && DummyRsa::elem(x.clone()).0.gcd(DummyRsa::modulus()) == 1 {
+        assert!(DummyRsa::exp(&DummyRsa::elem(x), &-n) == DummyRsa::inv(&expected));
+      }
+    }
+  }
+
+  #[test]
+  fn test_with_modulus() {
+    let mut rng = test_rng(0);
+    for _ in 0..50 {
+      let modulus = DummyRsaVar::random_modulus(&mut rng, 16);
+      assert!(modulus.significant_bits() >= 31);
+      DummyRsaVar::with_modulus(modulus.clone(), || {
+        assert!(*DummyRsaVar::modulus() == modulus);
+        let x = DummyRsaVar::elem(12345);
+        assert!(DummyRsaVar::op(&x, &DummyRsaVar::inv(&x)) == DummyRsaVar::id());
+        assert!(DummyRsaVar::exp(&x, &int(-3)) == DummyRsaVar::inv(&DummyRsaVar::exp(&x, &int(3))));
+        let bytes = DummyRsaVar::to_bytes(&x);
+        assert!(DummyRsaVar::from_bytes(&bytes) == Some(x));
+      });
+    }
+    assert!(DummyRsaVar::modulus() == DummyRsa::modulus());
+  }
+
+  #[test]
+  fn test_random_modulus_small() {
+    let mut rng = test_rng(1);
+    for _ in 0..10 {
+      let modulus = DummyRsaVar::random_modulus(&mut rng, 6);
+      assert!(modulus == 47 * 59);
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "6 to 32 bits")]
+  fn test_random_modulus_too_small() {
+    DummyRsaVar::random_modulus(&mut test_rng(0), 5);
+  }
+
+  #[test]
+  fn test_unknown_order_elem_in_range() {
+    let g = DummyRsa::unknown_order_elem();
+    assert!(g.0 >= 0 && g.0 < *DummyRsa::modulus());
+    let g = DummyRsa2::unknown_order_elem();
+    assert!(g.0 >= 0 && g.0 < *DummyRsa2::modulus());
+  }
+}
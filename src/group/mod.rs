@@ -4,18 +4,109 @@
 //!
 //! The preferred elliptic group implementation is the `Ristretto` group, which is a cyclic subset
 //! of the `Ed25519` group.
+use crate::hash::{hash, Blake2b};
 use crate::util::{int, TypeRep};
+use rug::integer::Order;
 use rug::Integer;
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::marker::Sized;
 
+// Implements `Group` for an RSA group `$group` with elements `$elem(pub Integer)` of `$elem_bytes`
+// bytes, given a `TypeRep` impl whose `Rep` is the modulus and an `ElemFrom` impl that reduces into
+// `[0, N)` and picks the smaller of `x` and `N - x`. Shared by `Rsa2048` and the dummy groups, so
+// that a new RSA group only has to declare its modulus and its `UnknownOrderGroup` impl.
+macro_rules! rsa_group_impls {
+  ($group:ident, $elem:ident, $elem_bytes:expr) => {
+    impl $crate::group::Group for $group {
+      type Elem = $elem;
+
+      const ELEM_BYTES: usize = $elem_bytes;
+
+      fn op_(modulus: &::rug::Integer, a: &$elem, b: &$elem) -> $elem {
+        // Identity short-circuit. Elements are stored reduced, so `1` is the only identity rep.
+        if a.0 == 1 {
+          return b.clone();
+        }
+        if b.0 == 1 {
+          return a.clone();
+        }
+        Self::elem($crate::util::int(&a.0 * &b.0) % modulus)
+      }
+
+      fn id_(_: &::rug::Integer) -> $elem {
+        Self::elem(1)
+      }
+
+      fn is_id_(_: &::rug::Integer, x: &$elem) -> bool {
+        x.0 == 1
+      }
+
+      fn inv_(modulus: &::rug::Integer, x: &$elem) -> $elem {
+        Self::elem(x.0.invert_ref(modulus).unwrap())
+      }
+
+      fn batch_inv_(modulus: &::rug::Integer, elems: &[$elem]) -> Vec<$elem> {
+        $crate::group::batch_invert_mod(modulus, elems.iter().map(|x| &x.0))
+          .into_iter()
+          .map(Self::elem)
+          .collect()
+      }
+
+      fn exp_(modulus: &::rug::Integer, x: &$elem, n: &::rug::Integer) -> $elem {
+        // A side-channel resistant impl is 40% slower; we'll consider it in the future if we need
+        // to.
+        Self::elem(x.0.pow_mod_ref(n, modulus).unwrap())
+      }
+
+      fn to_bytes_(_: &::rug::Integer, x: &$elem) -> Vec<u8> {
+        $crate::util::to_fixed_be_bytes(&x.0, Self::ELEM_BYTES)
+      }
+
+      fn from_bytes_(modulus: &::rug::Integer, bytes: &[u8]) -> Option<$elem> {
+        if bytes.len() != Self::ELEM_BYTES {
+          return None;
+        }
+        let x = ::rug::Integer::from_digits(bytes, ::rug::integer::Order::Msf);
+        if x >= *modulus || $crate::util::int(x.gcd_ref(modulus)) != 1 {
+          return None;
+        }
+        // `from_bytes` normalizes `x` and `modulus - x` to the same representative.
+        Some($elem(x))
+      }
+
+      fn descriptor_(modulus: &::rug::Integer) -> $crate::group::GroupDescriptor {
+        $crate::group::GroupDescriptor {
+          family: "rsa",
+          params: modulus.to_digits(::rug::integer::Order::Msf),
+        }
+      }
+
+      fn normalize_elem_(_: &::rug::Integer, x: &mut $elem) {
+        *x = Self::elem(x.0.clone());
+      }
+
+      // For moduli `N = 1 mod 4`, e.g. products of two safe primes, `x` and `-x` have the same
+      // Jacobi symbol, so the check does not depend on which of them represents the element.
+      fn in_subgroup_(modulus: &::rug::Integer, x: &$elem) -> bool {
+        let doubled: ::rug::Integer = $crate::util::int(&x.0) << 1;
+        x.0 > 0 && doubled < *modulus && x.0.jacobi(modulus) == 1
+      }
+    }
+  };
+}
+
 mod class;
 pub use class::{ClassElem, ClassGroup};
+#[cfg(test)]
+mod dummy;
+#[cfg(test)]
+pub use dummy::{DummyRsa, DummyRsa2, DummyRsa2Elem, DummyRsaElem, DummyRsaVar, DummyRsaVarElem};
 mod ristretto;
 pub use ristretto::{Ristretto, RistrettoElem};
 mod rsa;
-pub use rsa::{Rsa2048, Rsa2048Elem};
+pub use rsa::{is_valid_rsa_modulus, ElemPrecomp, Rsa2048, Rsa2048Elem};
 
 /// A mathematical group.
 ///
@@ -24,6 +115,7 @@ pub use rsa::{Rsa2048, Rsa2048Elem};
 /// - Op (the fundamental group operation)
 /// - Exponentiation
 /// - Inverse (particularly where this is efficient to compute)
+/// - Canonical byte encoding (and decoding)
 ///
 /// The `TypeRep` trait lets us emulate type-level static fields, e.g. the modulus in an RSA group
 /// or the discriminant in a class group.
@@ -31,7 +123,7 @@ pub use rsa::{Rsa2048, Rsa2048Elem};
 /// Clients of this trait need to implement functions of the form `*_`, which take in `TypeRep`
 /// data as a parameter. Consumers use functions without the underscore: `id`, `op`, `exp`, and
 /// `inv`.
-
+//
 // The other traits are only required here because Rust can't figure out how to do stuff with an
 // `Accumulator<G>` even though it's just a wrapped `G::Elem`. If possible we'd remove them.
 pub trait Group: Clone + Debug + Eq + Hash + TypeRep + Send + Sync {
@@ -43,6 +135,10 @@ pub trait Group: Clone + Debug + Eq + Hash + TypeRep + Send + Sync {
   /// The associated group element type for this group.
   type Elem: Clone + Debug + Eq + Hash + Sized + Send + Sync;
 
+  /// The length of the encoding `to_bytes` of every element, for callers that preallocate buffers
+  /// or account for proof sizes.
+  const ELEM_BYTES: usize;
+
   /// A group-specific wrapper for `id`.
   fn id_(rep: &Self::Rep) -> Self::Elem;
 
@@ -75,10 +171,25 @@ pub trait Group: Clone + Debug + Eq + Hash + TypeRep + Send + Sync {
   /// A group-specific wrapper for `inv`.
   fn inv_(rep: &Self::Rep, a: &Self::Elem) -> Self::Elem;
 
+  /// A group-specific wrapper for `to_bytes`.
+  fn to_bytes_(rep: &Self::Rep, a: &Self::Elem) -> Vec<u8>;
+
+  /// A group-specific wrapper for `from_bytes`.
+  fn from_bytes_(rep: &Self::Rep, bytes: &[u8]) -> Option<Self::Elem>;
+
+  /// A group-specific wrapper for `descriptor`.
+  fn descriptor_(rep: &Self::Rep) -> GroupDescriptor;
+
   // -------------------
   // END OF REQUIRED FNS
   // -------------------
 
+  /// A group-specific wrapper for `square`. Defaults to `op_(rep, a, a)`, which groups with a
+  /// dedicated squaring can override.
+  fn square_(rep: &Self::Rep, a: &Self::Elem) -> Self::Elem {
+    Self::op_(rep, a, a)
+  }
+
   /// Returns the identity element of the group.
   fn id() -> Self::Elem {
     Self::id_(Self::rep())
@@ -89,15 +200,166 @@ pub trait Group: Clone + Debug + Eq + Hash + TypeRep + Send + Sync {
     Self::op_(Self::rep(), a, b)
   }
 
-  /// Applies the group operation to `a` and itself `n` times and returns the result.
+  /// Like `op`, but borrows the other operand instead of cloning it when `a` or `b` is the
+  /// identity. Useful in verification loops where identity operands are common.
+  fn op_ref<'a>(a: &'a Self::Elem, b: &'a Self::Elem) -> Cow<'a, Self::Elem> {
+    if Self::is_id(a) {
+      Cow::Borrowed(b)
+    } else if Self::is_id(b) {
+      Cow::Borrowed(a)
+    } else {
+      Cow::Owned(Self::op(a, b))
+    }
+  }
+
+  /// Returns `a` applied to itself, i.e. `op(a, a)`, possibly computed faster.
+  fn square(a: &Self::Elem) -> Self::Elem {
+    Self::square_(Self::rep(), a)
+  }
+
+  /// Returns whether `a` is the identity element.
+  fn is_id(a: &Self::Elem) -> bool {
+    Self::is_id_(Self::rep(), a)
+  }
+
+  /// Applies the group operation to `a` and itself `n` times and returns the result. Negative `n`
+  /// exponentiates the inverse of `a`, and `n = 0` always gives the identity.
   fn exp(a: &Self::Elem, n: &Integer) -> Self::Elem {
+    // Proofs such as `Poke2` can hit zero exponents; return the identity here rather than relying
+    // on every `exp_` to handle it.
+    if *n == 0 {
+      return Self::id();
+    }
     Self::exp_(Self::rep(), a, n)
   }
 
+  /// Like `exp`, but also returns whether `a` was inverted, i.e. whether `n` is negative. Useful
+  /// for debugging the sign handling of signed exponents such as Bezout coefficients.
+  fn exp_signed_tracked(a: &Self::Elem, n: &Integer) -> (Self::Elem, bool) {
+    (Self::exp(a, n), *n < 0)
+  }
+
   /// Returns the group inverse of `a`.
   fn inv(a: &Self::Elem) -> Self::Elem {
     Self::inv_(Self::rep(), a)
   }
+
+  /// Returns the inverses of `elems`, in order.
+  fn batch_inv(elems: &[Self::Elem]) -> Vec<Self::Elem> {
+    Self::batch_inv_(Self::rep(), elems)
+  }
+
+  /// Returns whether `a ^ k` is the identity for some `1 <= k <= bound`, i.e. whether `a` has order
+  /// at most `bound`. Low-order elements such as `-1` in an RSA group break the proofs in this
+  /// crate, so callers accepting untrusted elements can reject them with this check.
+  ///
+  /// Costs up to `bound` group operations.
+  fn has_small_order(a: &Self::Elem, bound: u64) -> bool {
+    let id = Self::id();
+    let mut power = a.clone();
+    for _ in 0..bound {
+      if power == id {
+        return true;
+      }
+      power = Self::op(&power, a);
+    }
+    false
+  }
+
+  /// Returns whether `a` is a canonical representative of an element of the subgroup that this
+  /// crate works in, as far as that can be checked efficiently. Like `has_small_order`, this lets
+  /// callers accepting untrusted elements, e.g. witnesses or accumulator values received over the
+  /// network, reject malformed ones before using them.
+  ///
+  /// For RSA groups, this checks that the representative is reduced and has Jacobi symbol `1`,
+  /// which every quadratic residue has; full quadratic residuosity cannot be decided without the
+  /// factorization of the modulus. For class groups, it checks that the form is a reduced,
+  /// primitive form of the group's discriminant.
+  fn in_subgroup(a: &Self::Elem) -> bool {
+    Self::in_subgroup_(Self::rep(), a)
+  }
+
+  /// Returns a canonical, fixed-width byte encoding of `a`. Equal elements have equal encodings
+  /// regardless of how they were constructed, so the output is suitable for hashing or comparing
+  /// against other implementations.
+  fn to_bytes(a: &Self::Elem) -> Vec<u8> {
+    Self::to_bytes_(Self::rep(), a)
+  }
+
+  /// Decodes an element from the encoding produced by `to_bytes`. Returns `None` if `bytes` does
+  /// not encode a valid element of the group.
+  ///
+  /// The element is normalized as by `normalize_elem`, so that it compares and hashes equal to the
+  /// same element obtained any other way, even if `bytes` holds a valid but non-canonical
+  /// representative, e.g. `N - x` for an RSA group or an unreduced form for a class group.
+  fn from_bytes(bytes: &[u8]) -> Option<Self::Elem> {
+    let mut a = Self::from_bytes_(Self::rep(), bytes)?;
+    Self::normalize_elem(&mut a);
+    Some(a)
+  }
+
+  /// Returns a descriptor identifying this group instance.
+  fn descriptor() -> GroupDescriptor {
+    Self::descriptor_(Self::rep())
+  }
+
+  /// Brings `a` into the canonical representation of its element, e.g. the reduced form of a class
+  /// group element. Group operations always return canonical elements, so this is only needed for
+  /// elements assembled by hand.
+  fn normalize_elem(a: &mut Self::Elem) {
+    Self::normalize_elem_(Self::rep(), a)
+  }
+
+  /// Normalizes every element of `elems` as by `normalize_elem`. Groups can override this to share
+  /// work across the slice.
+  fn batch_normalize(elems: &mut [Self::Elem]) {
+    for a in elems {
+      Self::normalize_elem(a);
+    }
+  }
+
+  /// A group-specific wrapper for `batch_inv`. Defaults to inverting each element, which groups
+  /// with expensive inversion (e.g. RSA groups) can override with Montgomery's trick.
+  fn batch_inv_(rep: &Self::Rep, elems: &[Self::Elem]) -> Vec<Self::Elem> {
+    elems.iter().map(|a| Self::inv_(rep, a)).collect()
+  }
+
+  /// A group-specific wrapper for `normalize_elem`. Defaults to a no-op, which is correct for
+  /// groups whose elements have a single representation.
+  fn normalize_elem_(_rep: &Self::Rep, _a: &mut Self::Elem) {}
+
+  /// A group-specific wrapper for `in_subgroup`. Defaults to `true`, which is correct for groups
+  /// whose elements are all valid and of the intended order, e.g. prime-order groups.
+  fn in_subgroup_(_rep: &Self::Rep, _a: &Self::Elem) -> bool {
+    true
+  }
+
+  /// A group-specific wrapper for `is_id`. Defaults to comparing against `id_`, which groups can
+  /// override to avoid constructing the identity.
+  fn is_id_(rep: &Self::Rep, a: &Self::Elem) -> bool {
+    *a == Self::id_(rep)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Identifies a concrete group instance at runtime, e.g. an RSA group together with its modulus.
+/// Two groups with equal descriptors have interchangeable elements.
+pub struct GroupDescriptor {
+  /// The family of the group, e.g. `"rsa"` or `"class"`.
+  pub family: &'static str,
+  /// The big-endian magnitude of the parameter fixing the group within its family (the modulus or
+  /// discriminant). Empty for families with a single group.
+  pub params: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The different types of errors when decoding data tied to a group instance.
+pub enum GroupError {
+  /// The data was produced over a group other than the one decoding it.
+  DescriptorMismatch,
+
+  /// The data is not a valid encoding.
+  Malformed,
 }
 
 /// A group containing elements of unknown order.
@@ -106,12 +368,82 @@ pub trait Group: Clone + Debug + Eq + Hash + TypeRep + Send + Sync {
 #[allow(clippy::module_name_repetitions)]
 pub trait UnknownOrderGroup: Group {
   /// Returns an element of unknown order in the group.
+  ///
+  /// RSA groups must return a quadratic residue (e.g. `4` rather than `2`), so that everything
+  /// derived from it stays in the QR subgroup, which has no known elements of small order.
   fn unknown_order_elem() -> Self::Elem {
     Self::unknown_order_elem_(Self::rep())
   }
 
   /// A group-specific wrapper for `unknown_order_elem`.
   fn unknown_order_elem_(rep: &Self::Rep) -> Self::Elem;
+
+  /// Returns `unknown_order_elem() ^ n`, where `n` may be negative. Groups can override
+  /// `exp_unknown_order_elem_` with a fast path that exploits the fixed base, e.g. a precomputed
+  /// table.
+  fn exp_unknown_order_elem(n: &Integer) -> Self::Elem {
+    Self::exp_unknown_order_elem_(Self::rep(), n)
+  }
+
+  /// A group-specific wrapper for `exp_unknown_order_elem`. Defaults to a plain `exp`.
+  fn exp_unknown_order_elem_(rep: &Self::Rep, n: &Integer) -> Self::Elem {
+    Self::exp_(rep, &Self::unknown_order_elem_(rep), n)
+  }
+
+  /// Deterministically derives an element of unknown order from `seed` by hashing it into the
+  /// group. Unlike `exp(unknown_order_elem(), h)`, nobody knows a discrete log of the result
+  /// relative to `unknown_order_elem` or to the generator for any other seed, so structures built
+  /// on different seeds are independent. RSA groups must again return a quadratic residue.
+  fn generator(seed: &[u8]) -> Self::Elem {
+    Self::generator_(Self::rep(), seed)
+  }
+
+  /// A group-specific wrapper for `generator`.
+  fn generator_(rep: &Self::Rep, seed: &[u8]) -> Self::Elem;
+}
+
+/// Inverts every element of `xs` modulo `modulus` with Montgomery's trick: one modular inversion
+/// of the product of all elements, plus three multiplications per element. Panics if some element
+/// is not invertible.
+pub(crate) fn batch_invert_mod<'a>(
+  modulus: &Integer,
+  xs: impl ExactSizeIterator<Item = &'a Integer>,
+) -> Vec<Integer> {
+  // prefixes[i] = xs[0] * ... * xs[i - 1].
+  let mut prefixes = Vec::with_capacity(xs.len() + 1);
+  prefixes.push(int(1));
+  let mut elems = Vec::with_capacity(xs.len());
+  for x in xs {
+    let prefix = int(prefixes.last().unwrap() * x) % modulus;
+    prefixes.push(prefix);
+    elems.push(x);
+  }
+  let mut inv = prefixes.pop().unwrap().invert(modulus).unwrap();
+  let mut invs = vec![int(0); elems.len()];
+  for (i, x) in elems.into_iter().enumerate().rev() {
+    // Here `inv = (xs[0] * ... * xs[i])^-1`.
+    invs[i] = int(&inv * &prefixes[i]) % modulus;
+    inv = inv * x % modulus;
+  }
+  invs
+}
+
+/// Hashes `seed` to a quadratic residue coprime to `modulus`, as the square of a Blake2b
+/// expansion of `seed` with 128 bits more than `modulus` (so the reduction is close to uniform).
+pub(crate) fn hash_to_qr(modulus: &Integer, seed: &[u8]) -> Integer {
+  let n_blocks = (modulus.significant_bits() + 128).div_ceil(256);
+  let mut counter = 0_u64;
+  loop {
+    let mut digits = Vec::with_capacity(32 * n_blocks as usize);
+    for block in 0..n_blocks {
+      digits.extend_from_slice(&hash(&Blake2b::default, &(seed, counter, block)));
+    }
+    let x = Integer::from_digits(&digits, Order::Msf) % modulus;
+    if int(x.gcd_ref(modulus)) == 1 {
+      return x.pow_mod(&int(2), modulus).unwrap();
+    }
+    counter += 1;
+  }
 }
 
 /// Like `From<T>`, but implemented on the `Group` instead of the element type.
@@ -120,6 +452,35 @@ pub trait ElemFrom<T>: Group {
   fn elem(val: T) -> Self::Elem;
 }
 
+/// Returns `(base ^ m, base ^ n)` for nonnegative `m` and `n`, walking one right-to-left chain of
+/// squarings of `base` for both instead of one per exponent. Only the current square is kept, so
+/// memory stays constant however long the exponents are.
+///
+/// Squares with `G::square`, but unlike a group's `exp_` does not use windowing.
+///
+/// Panics if `m` or `n` is negative.
+pub fn exp_pair<G: Group>(base: &G::Elem, m: &Integer, n: &Integer) -> (G::Elem, G::Elem) {
+  assert!(
+    *m >= 0 && *n >= 0,
+    "exp_pair requires nonnegative exponents"
+  );
+  let bits = m.significant_bits().max(n.significant_bits());
+  let (mut base_m, mut base_n) = (G::id(), G::id());
+  let mut square = base.clone();
+  for i in 0..bits {
+    if m.get_bit(i) {
+      base_m = G::op(&base_m, &square);
+    }
+    if n.get_bit(i) {
+      base_n = G::op(&base_n, &square);
+    }
+    if i + 1 < bits {
+      square = G::square(&square);
+    }
+  }
+  (base_m, base_n)
+}
+
 /// Computes the product of `alpha_i ^ (p(x) / x_i)`, where `i` is an index into the `alphas` and
 /// `x` arrays, and `p(x)` is the product of all `x_i`. See BBF (page 11).
 pub fn multi_exp<G: Group>(alphas: &[G::Elem], x: &[Integer]) -> G::Elem {
@@ -139,10 +500,59 @@ pub fn multi_exp<G: Group>(alphas: &[G::Elem], x: &[Integer]) -> G::Elem {
   G::op(&G::exp(&l, &x_star_r), &G::exp(&r, &x_star_l))
 }
 
+/// Returns the product of `bases[i] ^ exps[i]` by simultaneous (Straus) exponentiation: one chain
+/// of squarings as long as the longest exponent is shared by all bases, and each step multiplies in
+/// the precomputed product of the bases whose exponent has that bit set. Negative exponents
+/// exponentiate the inverse of their base.
+///
+/// The table of subset products has `2 ^ bases.len()` entries, so this is meant for a handful of
+/// bases, as in proof verification equations.
+///
+/// Panics if `bases` and `exps` have different lengths.
+pub fn multi_exp_simultaneous<G: Group>(bases: &[G::Elem], exps: &[Integer]) -> G::Elem {
+  assert_eq!(
+    bases.len(),
+    exps.len(),
+    "bases and exps must have the same length"
+  );
+  let bases = bases
+    .iter()
+    .zip(exps)
+    .map(|(base, exp)| if *exp < 0 { G::inv(base) } else { base.clone() })
+    .collect::<Vec<_>>();
+  let exps = exps.iter().map(|exp| exp.as_abs()).collect::<Vec<_>>();
+  // `table[mask]` is the product of the bases at the set bits of `mask`.
+  let mut table = vec![G::id()];
+  for base in &bases {
+    let with_base = table.iter().map(|t| G::op(t, base)).collect::<Vec<_>>();
+    table.extend(with_base);
+  }
+  let bits = exps
+    .iter()
+    .map(|exp| exp.significant_bits())
+    .max()
+    .unwrap_or(0);
+  (0..bits).rev().fold(G::id(), |acc, bit| {
+    let acc = G::op(&acc, &acc);
+    let mask = exps
+      .iter()
+      .enumerate()
+      .filter(|(_, exp)| exp.get_bit(bit))
+      .fold(0, |mask, (i, _)| mask | 1 << i);
+    if mask == 0 {
+      acc
+    } else {
+      G::op(&acc, &table[mask])
+    }
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::util::int;
+  use crate::util::{int, random_bits, test_rng};
+  use curve25519_dalek::constants::RISTRETTO_BASEPOINT_COMPRESSED;
+  use std::collections::HashSet;
 
   #[test]
   fn test_multi_exp() {
@@ -160,4 +570,226 @@ mod tests {
     let res_2 = multi_exp::<Rsa2048>(&[alpha_1, alpha_2, alpha_3], &[x_1, x_2, x_3]);
     assert!(res_2 == Rsa2048::elem(1_687_500));
   }
+
+  test_all_groups!(
+    test_multi_exp_simultaneous,
+    test_multi_exp_simultaneous_rsa2048,
+    test_multi_exp_simultaneous_class,
+  );
+  fn test_multi_exp_simultaneous<G: UnknownOrderGroup>() {
+    let g = G::unknown_order_elem();
+    let bases = [g.clone(), G::exp(&g, &int(3)), G::exp(&g, &int(7)), G::id()];
+    let exps = [int(1) << 100, int(-12345), int(0), int(99)];
+    let expected = bases
+      .iter()
+      .zip(&exps)
+      .fold(G::id(), |acc, (base, exp)| G::op(&acc, &G::exp(base, exp)));
+    assert!(multi_exp_simultaneous::<G>(&bases, &exps) == expected);
+    assert!(multi_exp_simultaneous::<G>(&bases[..1], &exps[..1]) == G::exp(&g, &exps[0]));
+    assert!(multi_exp_simultaneous::<G>(&bases[2..3], &exps[2..3]) == G::id());
+    assert!(multi_exp_simultaneous::<G>(&[], &[]) == G::id());
+  }
+
+  /// Tests that differently constructed representations of one element are a single `HashSet` key.
+  #[test]
+  fn test_elem_hash_set() {
+    let rsa_set = [
+      Rsa2048::elem(3),
+      Rsa2048::elem(int(Rsa2048::rep() - 3)),
+      Rsa2048::op(&Rsa2048::elem(-1), &Rsa2048::elem(-3)),
+      Rsa2048::from_bytes(&Rsa2048::to_bytes(&Rsa2048::elem(3))).unwrap(),
+    ]
+    .iter()
+    .cloned()
+    .collect::<HashSet<_>>();
+    assert_eq!(rsa_set.len(), 1);
+
+    let g = ClassGroup::unknown_order_elem();
+    let class_set = [
+      ClassGroup::exp(&g, &int(4)),
+      ClassGroup::op(&ClassGroup::square(&g), &ClassGroup::square(&g)),
+      ClassGroup::inv(&ClassGroup::exp(&g, &int(-4))),
+      // `(2, 1, c)` and the equivalent unreduced form `(2, 5, c + 3)`.
+      ClassGroup::exp(
+        &ClassGroup::elem((2, 5, int(1 - ClassGroup::rep()) / 8 + 3)),
+        &int(4),
+      ),
+    ]
+    .iter()
+    .cloned()
+    .collect::<HashSet<_>>();
+    assert_eq!(class_set.len(), 1);
+
+    let bp = Ristretto::from_bytes(RISTRETTO_BASEPOINT_COMPRESSED.as_bytes()).unwrap();
+    let ristretto_set = [
+      Ristretto::op(&bp, &bp),
+      Ristretto::exp(&bp, &int(2)),
+      Ristretto::inv(&Ristretto::exp(&Ristretto::inv(&bp), &int(2))),
+    ]
+    .iter()
+    .cloned()
+    .collect::<HashSet<_>>();
+    assert_eq!(ristretto_set.len(), 1);
+  }
+
+  fn assert_elem_bytes<G: Group>(elems: &[G::Elem]) {
+    for elem in elems {
+      assert_eq!(G::to_bytes(elem).len(), G::ELEM_BYTES);
+    }
+  }
+
+  #[test]
+  fn test_elem_bytes() {
+    let mut rng = test_rng(7);
+    let exps = (0..4)
+      .map(|_| random_bits(&mut rng, 256))
+      .collect::<Vec<_>>();
+    let rsa_elems = exps
+      .iter()
+      .map(|x| Rsa2048::exp(&Rsa2048::unknown_order_elem(), x))
+      .chain(vec![Rsa2048::id(), Rsa2048::elem(2)])
+      .collect::<Vec<_>>();
+    assert_elem_bytes::<Rsa2048>(&rsa_elems);
+    let class_elems = exps
+      .iter()
+      .map(|x| ClassGroup::exp(&ClassGroup::unknown_order_elem(), x))
+      .chain(vec![ClassGroup::id()])
+      .collect::<Vec<_>>();
+    assert_elem_bytes::<ClassGroup>(&class_elems);
+    let bp = Ristretto::from_bytes(RISTRETTO_BASEPOINT_COMPRESSED.as_bytes()).unwrap();
+    let ristretto_elems = exps
+      .iter()
+      .map(|x| Ristretto::exp(&bp, x))
+      .chain(vec![Ristretto::id()])
+      .collect::<Vec<_>>();
+    assert_elem_bytes::<Ristretto>(&ristretto_elems);
+    let dummy_elems = exps
+      .iter()
+      .map(|x| DummyRsa::exp(&DummyRsa::unknown_order_elem(), x))
+      .collect::<Vec<_>>();
+    assert_elem_bytes::<DummyRsa>(&dummy_elems);
+    let dummy_2_elems = exps
+      .iter()
+      .map(|x| DummyRsa2::exp(&DummyRsa2::unknown_order_elem(), x))
+      .collect::<Vec<_>>();
+    assert_elem_bytes::<DummyRsa2>(&dummy_2_elems);
+  }
+
+  test_all_groups!(test_generator, test_generator_rsa2048, test_generator_class,);
+  fn test_generator<G: UnknownOrderGroup>() {
+    let g = G::generator(b"seed");
+    assert!(g == G::generator(b"seed"));
+    assert!(g != G::generator(b"other seed"));
+    assert!(g != G::unknown_order_elem());
+    assert!(!G::is_id(&g));
+    assert!(G::from_bytes(&G::to_bytes(&g)) == Some(g));
+  }
+
+  test_all_groups!(test_exp_zero, test_exp_zero_rsa2048, test_exp_zero_class,);
+  fn test_exp_zero<G: UnknownOrderGroup>() {
+    let g = G::unknown_order_elem();
+    assert!(G::is_id(&G::exp(&g, &int(0))));
+    assert!(G::is_id(&G::exp(&G::id(), &int(0))));
+    assert!(G::is_id(&G::exp(&G::inv(&g), &int(0))));
+    assert!(G::exp(&g, &(int(1) - 1)) == G::id());
+  }
+
+  #[test]
+  fn test_exp_zero_dummy() {
+    assert!(DummyRsa::exp(&DummyRsa::elem(3), &int(0)) == DummyRsa::id());
+  }
+
+  #[test]
+  fn test_generator_dummy() {
+    let g = DummyRsa::generator(b"seed");
+    assert!(g.0 > 1 && g.0 < *DummyRsa::modulus());
+    // `g` is a square up to sign (`x` and `-x` are identified), and `-1` is a non-residue modulo
+    // both prime factors, so `g` is either a residue modulo both or modulo neither.
+    let (l_1, l_2) = (g.0.legendre(&int(1019)), g.0.legendre(&int(1187)));
+    assert!(l_1 != 0 && l_1 == l_2);
+  }
+
+  #[test]
+  fn test_batch_inv() {
+    let mut rng = test_rng(0);
+    let rsa_elems = (0..20)
+      .map(|_| Rsa2048::elem(random_bits(&mut rng, 2048)))
+      .collect::<Vec<_>>();
+    let rsa_invs = rsa_elems.iter().map(Rsa2048::inv).collect::<Vec<_>>();
+    assert!(Rsa2048::batch_inv(&rsa_elems) == rsa_invs);
+    assert!(Rsa2048::batch_inv(&rsa_elems[..1]) == rsa_invs[..1]);
+    assert!(Rsa2048::batch_inv(&[]).is_empty());
+
+    let dummy_elems = (1..20).map(DummyRsa::elem).collect::<Vec<_>>();
+    let dummy_invs = dummy_elems.iter().map(DummyRsa::inv).collect::<Vec<_>>();
+    assert!(DummyRsa::batch_inv(&dummy_elems) == dummy_invs);
+
+    let g = ClassGroup::unknown_order_elem();
+    let class_elems = (1..5)
+      .map(|i| ClassGroup::exp(&g, &int(i)))
+      .collect::<Vec<_>>();
+    let class_invs = class_elems.iter().map(ClassGroup::inv).collect::<Vec<_>>();
+    assert!(ClassGroup::batch_inv(&class_elems) == class_invs);
+  }
+
+  test_all_groups!(test_square, test_square_rsa2048, test_square_class,);
+  fn test_square<G: UnknownOrderGroup>() {
+    let g = G::unknown_order_elem();
+    for x in &[G::id(), g.clone(), G::inv(&g), G::exp(&g, &int(12345))] {
+      assert!(G::square(x) == G::op(x, x));
+    }
+  }
+
+  test_all_groups!(test_exp_pair, test_exp_pair_rsa2048, test_exp_pair_class,);
+  fn test_exp_pair<G: UnknownOrderGroup>() {
+    let g = G::unknown_order_elem();
+    for (m, n) in &[
+      (int(0), int(0)),
+      (int(1), int(0)),
+      (int(12345), int(1) << 200),
+      ((int(1) << 300) - 1, int(7)),
+    ] {
+      assert!(exp_pair::<G>(&g, m, n) == (G::exp(&g, m), G::exp(&g, n)));
+    }
+  }
+
+  #[test]
+  fn test_op_ref() {
+    let x = Rsa2048::elem(3);
+    let y = Rsa2048::elem(5);
+    let id = Rsa2048::id();
+    assert!(Rsa2048::is_id(&id));
+    assert!(!Rsa2048::is_id(&x));
+    match (Rsa2048::op_ref(&id, &x), Rsa2048::op_ref(&x, &id)) {
+      (Cow::Borrowed(a), Cow::Borrowed(b)) => {
+        assert!(std::ptr::eq(a, &x));
+        assert!(std::ptr::eq(b, &x));
+      }
+      _ => panic!("expected borrowed results for identity operands"),
+    }
+    match Rsa2048::op_ref(&x, &y) {
+      Cow::Owned(xy) => assert!(xy == Rsa2048::op(&x, &y)),
+      Cow::Borrowed(_) => panic!("expected an owned result"),
+    }
+
+    let g = ClassGroup::unknown_order_elem();
+    let class_id = ClassGroup::id();
+    assert!(ClassGroup::is_id(&class_id));
+    assert!(*ClassGroup::op_ref(&class_id, &g) == g);
+    assert!(*ClassGroup::op_ref(&g, &g) == ClassGroup::square(&g));
+  }
+
+  #[test]
+  fn test_exp_signed_tracked() {
+    let base = Rsa2048::elem(3);
+    let (res, inverted) = Rsa2048::exp_signed_tracked(&base, &int(5));
+    assert!(res == Rsa2048::elem(243));
+    assert!(!inverted);
+    let (res, inverted) = Rsa2048::exp_signed_tracked(&base, &int(-5));
+    assert!(res == Rsa2048::inv(&Rsa2048::elem(243)));
+    assert!(inverted);
+    let (res, inverted) = Rsa2048::exp_signed_tracked(&base, &int(0));
+    assert!(res == Rsa2048::id());
+    assert!(!inverted);
+  }
 }
@@ -1,7 +1,7 @@
 //! Ristretto group implementation (based on the `curve25519-dalek` crate).
-use super::Group;
+use super::{Group, GroupDescriptor};
 use crate::util::{int, TypeRep};
-use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::Identity;
 use rug::integer::Order;
@@ -38,7 +38,7 @@ impl Ristretto {
 /// A Ristretto group element, directly wrapping a Ristretto point.
 pub struct RistrettoElem(RistrettoPoint);
 
-#[allow(clippy::derive_hash_xor_eq)]
+#[allow(clippy::derived_hash_with_manual_eq)]
 impl Hash for RistrettoElem {
   fn hash<H: Hasher>(&self, state: &mut H) {
     self.0.compress().as_bytes().hash(state);
@@ -55,6 +55,9 @@ impl TypeRep for Ristretto {
 impl Group for Ristretto {
   type Elem = RistrettoElem;
 
+  // The length of a compressed Ristretto point.
+  const ELEM_BYTES: usize = 32;
+
   fn op_(_: &(), a: &RistrettoElem, b: &RistrettoElem) -> RistrettoElem {
     RistrettoElem(a.0 + b.0)
   }
@@ -67,6 +70,26 @@ impl Group for Ristretto {
     RistrettoElem(-x.0)
   }
 
+  fn to_bytes_(_: &(), x: &RistrettoElem) -> Vec<u8> {
+    x.0.compress().as_bytes().to_vec()
+  }
+
+  fn from_bytes_(_: &(), bytes: &[u8]) -> Option<RistrettoElem> {
+    if bytes.len() != Self::ELEM_BYTES {
+      return None;
+    }
+    CompressedRistretto::from_slice(bytes)
+      .decompress()
+      .map(RistrettoElem)
+  }
+
+  fn descriptor_(_: &()) -> GroupDescriptor {
+    GroupDescriptor {
+      family: "ristretto",
+      params: vec![],
+    }
+  }
+
   fn exp_(_: &(), x: &RistrettoElem, n: &Integer) -> RistrettoElem {
     let mut remaining = n.clone();
     let mut result = Self::id();
@@ -105,4 +128,12 @@ mod tests {
     let exp_b_2 = Ristretto::exp(&exp_b, &int(2));
     assert_eq!(exp_a, exp_b_2);
   }
+
+  #[test]
+  fn test_from_bytes() {
+    let bp = RistrettoElem(constants::RISTRETTO_BASEPOINT_POINT);
+    let bytes = Ristretto::to_bytes(&bp);
+    assert_eq!(Ristretto::from_bytes(&bytes), Some(bp));
+    assert_eq!(Ristretto::from_bytes(&bytes[1..]), None);
+  }
 }
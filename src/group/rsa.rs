@@ -1,6 +1,7 @@
 //! RSA (2048) group using GMP integers in the `rug` crate.
-use super::{ElemFrom, Group, UnknownOrderGroup};
+use super::{hash_to_qr, ElemFrom, Group, UnknownOrderGroup};
 use crate::util::{int, TypeRep};
+use rug::integer::IsPrime;
 use rug::Integer;
 use std::str::FromStr;
 
@@ -22,9 +23,40 @@ const RSA2048_MODULUS_DECIMAL: &str =
   0924616515723350778707749817125772467962926386356373289912154831438167899885040445364023527381951\
   378636564391212010397122822120720357";
 
+/// Window width, in bits, of the table behind `exp_unknown_order_elem`.
+const GENERATOR_TABLE_WINDOW: u32 = 4;
+
+/// Largest exponent bit length served by the table behind `exp_unknown_order_elem`. Larger
+/// exponents fall back to `exp`.
+const GENERATOR_TABLE_BITS: u32 = 2048;
+
 lazy_static! {
   pub static ref RSA2048_MODULUS: Integer = Integer::from_str(RSA2048_MODULUS_DECIMAL).unwrap();
   pub static ref HALF_MODULUS: Integer = RSA2048_MODULUS.clone() / 2;
+
+  // Returned by `unknown_order_elem`, which many hot paths call, so that it costs a clone rather
+  // than a reduction mod `N`.
+  static ref UNKNOWN_ORDER_ELEM: Rsa2048Elem = Rsa2048::elem(4);
+
+  // `GENERATOR_TABLE[j][d - 1] = g ^ (d * 2 ^ (w * j))`, where `g = unknown_order_elem()` and `w`
+  // is the window width, so that `g ^ n` takes one multiplication per nonzero window of `n` and no
+  // squarings. Takes about 2 MB.
+  static ref GENERATOR_TABLE: Vec<Vec<Integer>> = {
+    let modulus = &*RSA2048_MODULUS;
+    let n_windows = GENERATOR_TABLE_BITS / GENERATOR_TABLE_WINDOW;
+    let mut table = Vec::with_capacity(n_windows as usize);
+    let mut base = Rsa2048::unknown_order_elem().0;
+    for _ in 0..n_windows {
+      let mut row = Vec::with_capacity((1 << GENERATOR_TABLE_WINDOW) - 1);
+      row.push(base.clone());
+      for d in 1..(1 << GENERATOR_TABLE_WINDOW) - 1 {
+        row.push(int(&row[d - 1] * &base) % modulus);
+      }
+      base = int(&row[row.len() - 1] * &base) % modulus;
+      table.push(row);
+    }
+    table
+  };
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -32,42 +64,106 @@ lazy_static! {
 /// An RSA 2048 group element, directly wrapping a GMP integer from the `rug` crate.
 pub struct Rsa2048Elem(pub Integer);
 
-impl TypeRep for Rsa2048 {
-  type Rep = Integer;
-  fn rep() -> &'static Self::Rep {
-    &RSA2048_MODULUS
-  }
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A table of the first `2 ^ window` powers of an RSA group element, used to speed up repeated
+/// exponentiation of that element. See `Rsa2048::precompute_elem`.
+pub struct ElemPrecomp {
+  window: usize,
+  powers: Vec<Integer>,
 }
 
-impl Group for Rsa2048 {
-  type Elem = Rsa2048Elem;
-  fn op_(modulus: &Integer, a: &Rsa2048Elem, b: &Rsa2048Elem) -> Rsa2048Elem {
-    Self::elem(int(&a.0 * &b.0) % modulus)
+impl Rsa2048 {
+  /// Returns the RSA-2048 challenge modulus.
+  pub fn modulus() -> &'static Integer {
+    Self::rep()
   }
 
-  fn id_(_: &Integer) -> Rsa2048Elem {
-    Self::elem(1)
+  /// Precomputes `x ^ i` for `0 <= i < 2 ^ window`, for use with `exp_elem_precomp`. Worthwhile
+  /// when the same element is raised to many different exponents, e.g. a witness used in several
+  /// aggregations.
+  ///
+  /// Panics if `window` is not between 1 and 16.
+  pub fn precompute_elem(x: &Rsa2048Elem, window: usize) -> ElemPrecomp {
+    assert!(
+      (1..=16).contains(&window),
+      "window must be between 1 and 16"
+    );
+    let modulus = Self::rep();
+    let mut powers = Vec::with_capacity(1 << window);
+    powers.push(int(1));
+    for i in 1..1 << window {
+      powers.push(int(&powers[i - 1] * &x.0) % modulus);
+    }
+    ElemPrecomp { window, powers }
   }
 
-  fn inv_(modulus: &Integer, x: &Rsa2048Elem) -> Rsa2048Elem {
-    Self::elem(x.0.invert_ref(modulus).unwrap())
+  /// Computes `x ^ n` using the table for `x` produced by `precompute_elem`, scanning `n` in
+  /// fixed windows from the most significant end.
+  pub fn exp_elem_precomp(precomp: &ElemPrecomp, n: &Integer) -> Rsa2048Elem {
+    let modulus = Self::rep();
+    let window = precomp.window as u32;
+    let n_abs = int(n.abs_ref());
+    let n_windows = n_abs.significant_bits().div_ceil(window);
+    let mut val = int(1);
+    for i in (0..n_windows).rev() {
+      for _ in 0..window {
+        val.square_mut();
+        val %= modulus;
+      }
+      let digit = int(&n_abs >> (i * window))
+        .keep_bits(window)
+        .to_usize()
+        .unwrap();
+      if digit != 0 {
+        val *= &precomp.powers[digit];
+        val %= modulus;
+      }
+    }
+    let val = Self::elem(val);
+    if *n < 0 {
+      Self::inv(&val)
+    } else {
+      val
+    }
   }
+}
 
-  fn exp_(modulus: &Integer, x: &Rsa2048Elem, n: &Integer) -> Rsa2048Elem {
-    // A side-channel resistant impl is 40% slower; we'll consider it in the future if we need to.
-    Self::elem(x.0.pow_mod_ref(n, modulus).unwrap())
+/// Performs cheap sanity checks that `n` could be an RSA modulus, i.e. a product of two distinct
+/// primes: `n` must be odd, greater than 1, composite and not a perfect power (which rules out
+/// squares of primes).
+///
+/// **Note**: Passing these checks does not prove that `n` is a semiprime, which is infeasible
+/// without its factorization. E.g. a product of three primes passes, and so does a modulus with a
+/// small factor, which is easy to factor. Only use moduli from a trusted source.
+pub fn is_valid_rsa_modulus(n: &Integer) -> bool {
+  *n > 1 && n.is_odd() && n.is_probably_prime(30) == IsPrime::No && !n.is_perfect_power()
+}
+
+impl TypeRep for Rsa2048 {
+  type Rep = Integer;
+  fn rep() -> &'static Self::Rep {
+    &RSA2048_MODULUS
   }
 }
 
+// `ELEM_BYTES` is the byte length of the 2048-bit modulus.
+rsa_group_impls!(Rsa2048, Rsa2048Elem, 256);
+
+/// Reduces `t` into `[0, N)`, so negative and oversized inputs map to the same element as their
+/// residue, and then picks the smaller of `x` and `N - x`.
 impl<T> ElemFrom<T> for Rsa2048
 where
   Integer: From<T>,
 {
   fn elem(t: T) -> Rsa2048Elem {
     let modulus = Self::rep();
-    let val = int(t) % modulus;
+    // `%` truncates, so only negative `t` needs shifting into `[0, N)`.
+    let mut val = int(t) % modulus;
+    if val < 0 {
+      val += modulus;
+    }
     if val > *HALF_MODULUS {
-      Rsa2048Elem(<(Integer, Integer)>::from((-val).div_rem_euc_ref(&modulus)).1)
+      Rsa2048Elem(modulus - val)
     } else {
       Rsa2048Elem(val)
     }
@@ -75,14 +171,47 @@ where
 }
 
 impl UnknownOrderGroup for Rsa2048 {
+  // Returns `4 = 2^2`, so that the generator (and hence every accumulator value and witness) lies
+  // in the subgroup of quadratic residues, which excludes low-order elements like `-1`.
   fn unknown_order_elem_(_: &Integer) -> Rsa2048Elem {
-    Self::elem(2)
+    UNKNOWN_ORDER_ELEM.clone()
+  }
+
+  fn generator_(modulus: &Integer, seed: &[u8]) -> Rsa2048Elem {
+    Self::elem(hash_to_qr(modulus, seed))
+  }
+
+  fn exp_unknown_order_elem_(modulus: &Integer, n: &Integer) -> Rsa2048Elem {
+    let n_abs = int(n.abs_ref());
+    if n_abs.significant_bits() > GENERATOR_TABLE_BITS {
+      return Self::exp_(modulus, &Self::unknown_order_elem_(modulus), n);
+    }
+    let mut val = int(1);
+    let n_windows = n_abs.significant_bits().div_ceil(GENERATOR_TABLE_WINDOW);
+    for (j, row) in GENERATOR_TABLE.iter().take(n_windows as usize).enumerate() {
+      let digit = int(&n_abs >> (j as u32 * GENERATOR_TABLE_WINDOW))
+        .keep_bits(GENERATOR_TABLE_WINDOW)
+        .to_usize()
+        .unwrap();
+      if digit != 0 {
+        val *= &row[digit - 1];
+        val %= modulus;
+      }
+    }
+    let val = Self::elem(val);
+    if *n < 0 {
+      Self::inv_(modulus, &val)
+    } else {
+      val
+    }
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::hash::blake2b;
+  use crate::util::{random_bits, square_and_multiply, test_rng, to_fixed_be_bytes};
 
   #[test]
   fn test_init() {
@@ -97,6 +226,174 @@ mod tests {
     assert!(b == Rsa2048::elem(6));
   }
 
+  #[test]
+  fn test_elem() {
+    assert!(Rsa2048::elem(-2) == Rsa2048::elem(2));
+    assert!(Rsa2048::elem(-2).0 == 2);
+    assert!(Rsa2048::elem(int(Rsa2048::modulus() + 5)) == Rsa2048::elem(5));
+    assert!(Rsa2048::elem(int(-Rsa2048::modulus()) - 5) == Rsa2048::elem(5));
+    assert!(Rsa2048::elem(u64::MAX).0 == u64::MAX);
+  }
+
+  #[test]
+  fn test_op_id() {
+    let x = Rsa2048::elem(12345);
+    assert!(Rsa2048::op(&Rsa2048::id(), &x) == x);
+    assert!(Rsa2048::op(&x, &Rsa2048::id()) == x);
+  }
+
+  #[test]
+  fn test_from_bytes() {
+    let x = Rsa2048::elem(12345);
+    let bytes = Rsa2048::to_bytes(&x);
+    assert!(Rsa2048::from_bytes(&bytes) == Some(x));
+    assert!(Rsa2048::from_bytes(&bytes[1..]).is_none());
+    assert!(Rsa2048::from_bytes(&vec![0; bytes.len()]).is_none());
+    assert!(Rsa2048::from_bytes(&vec![0xff; bytes.len()]).is_none());
+  }
+
+  #[test]
+  fn test_from_bytes_unreduced() {
+    let x = Rsa2048::elem(12345);
+    // `N - 12345` is a valid encoding of the same element, but not the canonical one.
+    let bytes = to_fixed_be_bytes(&int(Rsa2048::rep() - 12345), Rsa2048::ELEM_BYTES);
+    assert_ne!(bytes, Rsa2048::to_bytes(&x));
+    let loaded = Rsa2048::from_bytes(&bytes).unwrap();
+    assert!(loaded == x);
+    assert!(loaded.0 == 12345);
+    assert_eq!(Rsa2048::to_bytes(&loaded), Rsa2048::to_bytes(&x));
+    assert!(Rsa2048::in_subgroup(&loaded) == Rsa2048::in_subgroup(&x));
+  }
+
+  #[test]
+  fn test_exp_elem_precomp() {
+    let x = Rsa2048::elem(1_234_567);
+    for &window in &[1, 4, 5] {
+      let precomp = Rsa2048::precompute_elem(&x, window);
+      for i in 0..50 {
+        let n: Integer = (blake2b(&i) * blake2b(&(i, window))) >> (i * 7_u32);
+        let n = if i % 5 == 0 { -n } else { n };
+        assert!(Rsa2048::exp_elem_precomp(&precomp, &n) == Rsa2048::exp(&x, &n));
+      }
+      assert!(Rsa2048::exp_elem_precomp(&precomp, &int(0)) == Rsa2048::id());
+    }
+  }
+
+  #[test]
+  fn test_unknown_order_elem_is_qr() {
+    let g = Rsa2048::unknown_order_elem();
+    let g_inv = Rsa2048::inv(&g);
+    assert!(g == Rsa2048::exp(&Rsa2048::elem(2), &int(2)));
+    assert!(g_inv == Rsa2048::exp(&Rsa2048::inv(&Rsa2048::elem(2)), &int(2)));
+    assert_eq!(g.0.jacobi(Rsa2048::modulus()), 1);
+    assert_eq!(g_inv.0.jacobi(Rsa2048::modulus()), 1);
+  }
+
+  #[test]
+  fn test_unknown_order_elem_across_threads() {
+    let expected = Rsa2048::exp(&Rsa2048::elem(2), &int(2));
+    let handles: Vec<_> = (0..8)
+      .map(|_| std::thread::spawn(|| (0..100).map(|_| Rsa2048::unknown_order_elem()).collect()))
+      .collect();
+    for handle in handles {
+      let elems: Vec<Rsa2048Elem> = handle.join().unwrap();
+      assert!(elems.iter().all(|g| *g == expected));
+    }
+  }
+
+  #[test]
+  fn test_unknown_order_elem_in_range() {
+    let g = Rsa2048::unknown_order_elem();
+    assert!(g.0 >= 0 && g.0 < *Rsa2048::modulus());
+  }
+
+  #[test]
+  fn test_has_small_order() {
+    // Built directly, since `elem` would map `-1` to the identity coset.
+    let minus_one = Rsa2048Elem(RSA2048_MODULUS.clone() - 1);
+    assert!(Rsa2048::has_small_order(&minus_one, 2));
+    assert!(!Rsa2048::has_small_order(&minus_one, 1));
+    assert!(Rsa2048::has_small_order(&Rsa2048::id(), 1));
+    assert!(!Rsa2048::has_small_order(&Rsa2048::id(), 0));
+    assert!(!Rsa2048::has_small_order(
+      &Rsa2048::unknown_order_elem(),
+      1000
+    ));
+  }
+
+  #[test]
+  fn test_in_subgroup() {
+    let g = Rsa2048::unknown_order_elem();
+    assert!(Rsa2048::in_subgroup(&g));
+    assert!(Rsa2048::in_subgroup(&Rsa2048::id()));
+    assert!(Rsa2048::in_subgroup(&Rsa2048::exp(&g, &int(1_000_003))));
+    assert!(Rsa2048::in_subgroup(&Rsa2048::generator(b"seed")));
+
+    // `-1` and `-4` built directly, bypassing the reduction of `elem`, which would map them to `1`
+    // and `4`.
+    let minus_one = Rsa2048Elem(RSA2048_MODULUS.clone() - 1);
+    assert!(!Rsa2048::in_subgroup(&minus_one));
+    assert!(!Rsa2048::in_subgroup(&Rsa2048Elem(
+      RSA2048_MODULUS.clone() - 4
+    )));
+    assert!(!Rsa2048::in_subgroup(&Rsa2048Elem(int(0))));
+    // `N = 5 mod 8`, so `2` is a quadratic non-residue.
+    assert!(!Rsa2048::in_subgroup(&Rsa2048::elem(2)));
+    assert!(!Rsa2048::in_subgroup(&Rsa2048::op(&g, &Rsa2048::elem(2))));
+  }
+
+  #[test]
+  fn test_is_valid_rsa_modulus() {
+    assert!(is_valid_rsa_modulus(&RSA2048_MODULUS));
+    assert!(is_valid_rsa_modulus(&int(1019 * 1187)));
+    assert!(!is_valid_rsa_modulus(&int(1019)));
+    assert!(!is_valid_rsa_modulus(&int(1019 * 1019)));
+    assert!(!is_valid_rsa_modulus(&int(1019 * 1019 * 1019)));
+    assert!(!is_valid_rsa_modulus(&int(2 * 1019)));
+    assert!(!is_valid_rsa_modulus(&int(1)));
+    assert!(!is_valid_rsa_modulus(&int(-1019 * 1187)));
+  }
+
+  #[test]
+  fn test_exp_unknown_order_elem() {
+    let g = Rsa2048::unknown_order_elem();
+    let mut rng = test_rng(0);
+    for &bits in &[1, 5, 64, 255, 256, 1000, 2047, 2048, 2049, 3000] {
+      let n = random_bits(&mut rng, bits);
+      assert!(Rsa2048::exp_unknown_order_elem(&n) == Rsa2048::exp(&g, &n));
+      let n = -n;
+      assert!(Rsa2048::exp_unknown_order_elem(&n) == Rsa2048::exp(&g, &n));
+    }
+    let n = (int(1) << GENERATOR_TABLE_BITS) - 1;
+    assert!(Rsa2048::exp_unknown_order_elem(&n) == Rsa2048::exp(&g, &n));
+    assert!(Rsa2048::exp_unknown_order_elem(&int(0)) == Rsa2048::id());
+  }
+
+  #[test]
+  fn test_batch_normalize() {
+    let modulus = &*RSA2048_MODULUS;
+    let raw = [
+      int(5),
+      int(modulus - 5),
+      int(modulus + 5),
+      int(modulus * 3) - 5,
+      int(-5),
+      int(modulus - 1),
+      HALF_MODULUS.clone() + 1,
+    ];
+    let mut batch = raw.iter().cloned().map(Rsa2048Elem).collect::<Vec<_>>();
+    Rsa2048::batch_normalize(&mut batch);
+    for (x, normalized) in raw.iter().zip(&batch) {
+      let mut single = Rsa2048Elem(x.clone());
+      Rsa2048::normalize_elem(&mut single);
+      assert!(single == *normalized);
+      assert!(normalized.0 >= 0 && normalized.0 <= *HALF_MODULUS);
+    }
+    assert!(batch[..5].iter().all(|x| *x == Rsa2048::elem(5)));
+    assert!(batch[5] == Rsa2048::id());
+    assert!(batch[6] == Rsa2048::elem(HALF_MODULUS.clone()));
+  }
+
   /// Tests that `-x` and `x` are treated as the same element.
   #[test]
   fn test_cosets() {
@@ -129,10 +426,38 @@ mod tests {
     dbg!(d);
   }
 
+  #[test]
+  fn test_exp_reference() {
+    let modulus = Rsa2048::modulus();
+    let mut rng = test_rng(0);
+    for i in 0..1000 {
+      let x = Rsa2048::elem(random_bits(&mut rng, 2048));
+      // Mostly short exponents as in proofs, with full-width ones as in accumulator updates.
+      let n = random_bits(&mut rng, if i % 50 == 0 { 2048 } else { 256 });
+      let expected = Rsa2048::elem(square_and_multiply(&x.0, &n, modulus));
+      assert!(Rsa2048::exp(&x, &n) == expected);
+      if i % 4 == 0 {
+        assert!(Rsa2048::exp(&x, &-n) == Rsa2048::inv(&expected));
+      }
+    }
+  }
+
   #[test]
   fn test_inv() {
     let x = Rsa2048::elem(2);
     let inv = Rsa2048::inv(&x);
     assert!(Rsa2048::op(&x, &inv) == Rsa2048::id());
   }
+
+  #[test]
+  fn test_to_bytes() {
+    let bytes = Rsa2048::to_bytes(&Rsa2048::elem(258));
+    assert_eq!(bytes.len(), 256);
+    assert_eq!(bytes[254..], [1, 2]);
+    assert!(bytes[..254].iter().all(|&b| b == 0));
+    assert_eq!(
+      Rsa2048::to_bytes(&Rsa2048::elem(3)),
+      Rsa2048::to_bytes(&Rsa2048::elem(RSA2048_MODULUS.clone() - 3))
+    );
+  }
 }
@@ -2,8 +2,12 @@
 //! exports the generalized `hash` function. Also exported is `hash_to_prime`, which works by
 //! repeatedly `hash`ing a value together with an incrementing nonce until the output is prime.
 use crate::uint::u256;
-use rug::integer::Order;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use rug::integer::{IsPrime, Order};
 use rug::Integer;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 
 mod blake2b;
@@ -31,7 +35,7 @@ pub trait GeneralHasher: Hasher {
 /// Hash using the general Hasher.
 ///
 /// This function takes in the hash constructor as an argument for convenience.
-pub fn hash<H: GeneralHasher, T: Hash + ?Sized>(new_hasher: &Fn() -> H, t: &T) -> H::Output {
+pub fn hash<H: GeneralHasher, T: Hash + ?Sized>(new_hasher: &dyn Fn() -> H, t: &T) -> H::Output {
   let mut h = new_hasher();
   t.hash(&mut h);
   h.finalize()
@@ -63,10 +67,138 @@ pub fn hash_to_prime<T: Hash + ?Sized>(t: &T) -> Integer {
   }
 }
 
+/// Hashes `t` to a prime of exactly `bits` bits, e.g. for uniformly sized proofs.
+///
+/// Like `hash_to_prime`, hashes with a counter until a prime is found, but stretches each candidate
+/// to `bits` bits by hashing successive 256-bit blocks and then sets its top and bottom bits.
+/// Since `primality::is_prob_prime` is limited to 256 bits, candidates are checked with GMP's
+/// Miller-Rabin test instead.
+///
+/// Panics if `bits` is less than 2.
+pub fn hash_to_prime_sized<T: Hash + ?Sized>(t: &T, bits: u32) -> Integer {
+  assert!(bits >= 2, "no prime has fewer than 2 bits");
+  let mut counter = 0_u64;
+  loop {
+    let mut candidate = Integer::new();
+    for block in 0..bits.div_ceil(256) {
+      candidate |= blake2b(&(t, counter, block)) << (256 * block);
+    }
+    candidate.keep_bits_mut(bits);
+    candidate.set_bit(bits - 1, true);
+    candidate.set_bit(0, true);
+    if candidate.is_probably_prime(30) != IsPrime::No {
+      return candidate;
+    }
+    counter += 1;
+  }
+}
+
+/// Hashes each of `ts` to a prime via `hash_to_prime`. With the `rayon` feature, the elements are
+/// hashed in parallel.
+pub fn hash_to_primes<T: Hash + Sync>(ts: &[T]) -> Vec<Integer> {
+  #[cfg(feature = "rayon")]
+  let primes = ts.par_iter().map(hash_to_prime).collect();
+  #[cfg(not(feature = "rayon"))]
+  let primes = ts.iter().map(hash_to_prime).collect();
+  primes
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Returned by `hash_to_distinct_primes` when two elements hash to the same prime, e.g. because
+/// they are equal.
+pub struct DuplicatePrime {
+  /// Index of the first element hashing to the duplicated prime.
+  pub first: usize,
+  /// Index of the second element hashing to the duplicated prime.
+  pub second: usize,
+}
+
+/// Like `hash_to_primes`, but fails if two elements hash to the same prime. Accumulating a
+/// duplicate prime would turn the accumulated set into a multiset (see the crate docs).
+pub fn hash_to_distinct_primes<T: Hash + Sync>(ts: &[T]) -> Result<Vec<Integer>, DuplicatePrime> {
+  let primes = hash_to_primes(ts);
+  let mut seen = HashMap::with_capacity(primes.len());
+  for (i, p) in primes.iter().enumerate() {
+    if let Some(&first) = seen.get(p) {
+      return Err(DuplicatePrime { first, second: i });
+    }
+    seen.insert(p, i);
+  }
+  Ok(primes)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// An integer that has passed a primality check, e.g. `Prime::try_from(7u64)`.
+pub struct Prime(Integer);
+
+impl Prime {
+  /// Returns the prime as an `Integer`.
+  pub fn as_integer(&self) -> &Integer {
+    &self.0
+  }
+}
+
+impl From<Prime> for Integer {
+  fn from(p: Prime) -> Integer {
+    p.0
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Returned by `Prime::try_from` for an input that is not prime.
+pub struct NotPrime(pub u64);
+
+/// Checks primality with `primality::is_prob_prime`, which is deterministic for 64-bit inputs.
+impl TryFrom<u64> for Prime {
+  type Error = NotPrime;
+
+  fn try_from(n: u64) -> Result<Self, NotPrime> {
+    if n >= 2 && primality::is_prob_prime(&u256(n)) {
+      Ok(Prime(Integer::from(n)))
+    } else {
+      Err(NotPrime(n))
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_prime_try_from() {
+    assert!(Prime::try_from(7).unwrap().as_integer() == &7);
+    assert_eq!(Prime::try_from(8), Err(NotPrime(8)));
+    for n in 0..2 {
+      assert_eq!(Prime::try_from(n), Err(NotPrime(n)));
+    }
+    assert!(Prime::try_from(2).is_ok());
+    // The largest 64-bit prime, and its neighbour.
+    let max = 18_446_744_073_709_551_557;
+    assert!(Integer::from(Prime::try_from(max).unwrap()) == max);
+    assert!(Prime::try_from(max + 2).is_err());
+    let small_primes = (0..100).filter(|&n| Prime::try_from(n).is_ok()).count();
+    assert_eq!(small_primes, 25);
+  }
+
+  #[test]
+  fn test_hash_to_prime_sized() {
+    for &bits in &[2, 3, 64, 128, 255, 256, 257, 264, 512] {
+      for i in 0..5 {
+        let p = hash_to_prime_sized(&i, bits);
+        assert_eq!(p.significant_bits(), bits);
+        assert!(p.is_probably_prime(50) != IsPrime::No);
+        if bits == 256 {
+          let mut digits = [0; 4];
+          p.write_digits(&mut digits, Order::Lsf);
+          assert!(primality::is_prob_prime(&u256(digits)));
+        }
+      }
+    }
+    assert!(hash_to_prime_sized("a", 264) == hash_to_prime_sized("a", 264));
+    assert!(hash_to_prime_sized("a", 264) != hash_to_prime_sized("b", 264));
+  }
+
   #[test]
   fn test_blake2() {
     let data = b"martian cyborg gerbil attack";
@@ -88,4 +220,22 @@ mod tests {
     h_2.write_digits(&mut digits2, Order::Lsf);
     assert!(primality::is_prob_prime(&u256(digits2)));
   }
+
+  #[test]
+  fn test_hash_to_distinct_primes() {
+    let items = (0..100_u32).collect::<Vec<_>>();
+    let primes = hash_to_distinct_primes(&items).unwrap();
+    assert_eq!(primes.len(), items.len());
+    for (item, p) in items.iter().zip(primes.iter()) {
+      assert_eq!(*p, hash_to_prime(item));
+      assert_ne!(p.is_probably_prime(30), rug::integer::IsPrime::No);
+    }
+    assert_eq!(
+      hash_to_distinct_primes(&["a", "b", "c", "b"]),
+      Err(DuplicatePrime {
+        first: 1,
+        second: 3
+      })
+    );
+  }
 }
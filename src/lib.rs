@@ -93,9 +93,9 @@
 //! Class groups are currently not performant for any meaningful use case. A pull request is in the
 //! works to drastically improve their performance using techniques learned from the
 //! [Chia VDF competition](https://github.com/Chia-Network/vdf-competition).
-#![allow(clippy::unknown_clippy_lints)]
+#![allow(unknown_lints)]
 #![allow(clippy::many_single_char_names)]
-#![allow(clippy::empty_enum)]
+#![allow(clippy::empty_enums)]
 #![warn(missing_docs)]
 
 #[macro_use]
@@ -104,8 +104,16 @@ extern crate lazy_static;
 #[macro_use]
 extern crate arrayref;
 
+#[cfg(test)]
+#[macro_use]
+mod test_macros;
+
 mod accumulator;
 pub use crate::accumulator::*;
+mod frozen_accumulator;
+pub use frozen_accumulator::*;
+mod tracked_accumulator;
+pub use tracked_accumulator::*;
 mod vector_commitment;
 pub use vector_commitment::*;
 
@@ -0,0 +1,97 @@
+//! Type-erased proofs, for code that stores or forwards proofs without being generic over the
+//! group (e.g. a relay holding proofs over several groups in one collection).
+use super::{Poe, Poke2};
+use crate::group::{GroupDescriptor, UnknownOrderGroup};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A serialized proof tagged with the descriptor of the group it was computed in.
+pub enum AnyProof {
+  /// A serialized `Poe`.
+  Poe {
+    /// The group the proof was computed in.
+    descriptor: GroupDescriptor,
+    /// The output of `Poe::to_bytes`.
+    bytes: Vec<u8>,
+  },
+
+  /// A serialized `Poke2`.
+  Poke2 {
+    /// The group the proof was computed in.
+    descriptor: GroupDescriptor,
+    /// The output of `Poke2::to_bytes`.
+    bytes: Vec<u8>,
+  },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A proof recovered from an `AnyProof` over a concrete group.
+pub enum TypedProof<G: UnknownOrderGroup> {
+  /// A `Poe`.
+  Poe(Poe<G>),
+
+  /// A `Poke2`.
+  Poke2(Poke2<G>),
+}
+
+impl AnyProof {
+  /// Returns the descriptor of the group the proof was computed in.
+  pub fn descriptor(&self) -> &GroupDescriptor {
+    match self {
+      AnyProof::Poe { descriptor, .. } | AnyProof::Poke2 { descriptor, .. } => descriptor,
+    }
+  }
+
+  /// Recovers the concrete proof over `G`. Returns `None` if the proof was computed in a group
+  /// other than `G` or its bytes are malformed.
+  pub fn into_typed<G: UnknownOrderGroup>(self) -> Option<TypedProof<G>> {
+    if *self.descriptor() != G::descriptor() {
+      return None;
+    }
+    match self {
+      AnyProof::Poe { bytes, .. } => Poe::from_bytes(&bytes).map(TypedProof::Poe),
+      AnyProof::Poke2 { bytes, .. } => Poke2::from_bytes(&bytes).map(TypedProof::Poke2),
+    }
+  }
+}
+
+impl<G: UnknownOrderGroup> From<&Poe<G>> for AnyProof {
+  fn from(proof: &Poe<G>) -> Self {
+    AnyProof::Poe {
+      descriptor: G::descriptor(),
+      bytes: proof.to_bytes(),
+    }
+  }
+}
+
+impl<G: UnknownOrderGroup> From<&Poke2<G>> for AnyProof {
+  fn from(proof: &Poke2<G>) -> Self {
+    AnyProof::Poke2 {
+      descriptor: G::descriptor(),
+      bytes: proof.to_bytes(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::{ClassGroup, Group, Rsa2048};
+  use crate::util::int;
+
+  #[test]
+  fn test_into_typed() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let poke2_proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let poe_proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let proofs = [AnyProof::from(&poke2_proof), AnyProof::from(&poe_proof)];
+    assert!(proofs
+      .iter()
+      .all(|p| *p.descriptor() == Rsa2048::descriptor()));
+
+    assert!(proofs[0].clone().into_typed::<Rsa2048>() == Some(TypedProof::Poke2(poke2_proof)));
+    assert!(proofs[1].clone().into_typed::<Rsa2048>() == Some(TypedProof::Poe(poe_proof)));
+    assert!(proofs[0].clone().into_typed::<ClassGroup>().is_none());
+  }
+}
@@ -4,9 +4,346 @@
 //! Use standalone with caution.
 //!
 //! Implementations are based on Section 3 of BBF.
+use crate::group::{Group, GroupDescriptor};
+use crate::hash::{blake2b, hash, primality, Blake2b};
+use crate::uint::u256;
+use rug::integer::Order;
+use rug::Integer;
+#[cfg(feature = "challenge-cache")]
+use std::collections::HashMap;
+use std::convert::TryFrom;
+#[cfg(feature = "challenge-cache")]
+use std::sync::Mutex;
+mod any;
+pub use any::{AnyProof, TypedProof};
 mod poe;
 pub use poe::Poe;
+mod poe_batch;
+pub use poe_batch::PoeBatch;
 mod pokcr;
 pub use pokcr::Pokcr;
 mod poke2;
-pub use poke2::Poke2;
+pub use poke2::{Poke2, Poke2Error, Poke2Verifier};
+mod poke2_batch;
+pub use poke2_batch::Poke2Batch;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The public inputs of a proof, against which it is verified via `Verifiable`.
+pub enum Statement<G: Group> {
+  /// `base ^ exp = result`, as proven by a `Poe`.
+  Exp {
+    /// The base.
+    base: G::Elem,
+    /// The exponent.
+    exp: Integer,
+    /// The result.
+    result: G::Elem,
+  },
+
+  /// The prover knows some exponent `exp` s.t. `base ^ exp = result`, as proven by a `Poke2`.
+  KnowledgeOfExp {
+    /// The base.
+    base: G::Elem,
+    /// The result.
+    result: G::Elem,
+  },
+}
+
+/// A proof that can be verified against a `Statement`, so that heterogeneous proofs can be handled
+/// uniformly, e.g. as `Box<dyn Verifiable<G>>`.
+pub trait Verifiable<G: Group> {
+  /// Verifies the proof against `statement`. Returns `false` if `statement` is not of the kind
+  /// the proof proves.
+  fn verify(&self, statement: &Statement<G>) -> bool;
+}
+
+/// Bit length of the Fiat-Shamir prime challenges used by the proofs in this module at the default
+/// security level, which is also the longest supported.
+const CHALLENGE_BITS: u32 = 256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// Named security levels for the proofs in this module, each determining the bit length of the
+/// Fiat-Shamir prime challenges. Shorter challenges make proving and verifying cheaper.
+pub enum SecurityLevel {
+  /// 128-bit challenges.
+  Bits128,
+
+  /// 192-bit challenges.
+  Bits192,
+
+  /// 256-bit challenges, the default.
+  #[default]
+  Bits256,
+}
+
+impl SecurityLevel {
+  /// Returns the bit length of the prime challenges at this level.
+  pub fn challenge_bits(self) -> u32 {
+    match self {
+      SecurityLevel::Bits128 => 128,
+      SecurityLevel::Bits192 => 192,
+      SecurityLevel::Bits256 => CHALLENGE_BITS,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A Fiat-Shamir transcript, from which proofs derive their challenges.
+///
+/// Every appended component is preceded by its length as a 4-byte big-endian integer, and the first
+/// component is a domain label, so transcripts for different protocols or with different sequences
+/// of components never encode to the same bytes.
+pub struct Transcript {
+  bytes: Vec<u8>,
+}
+
+impl Transcript {
+  /// Starts a transcript for the protocol identified by `domain`, e.g. `b"accumulator/poe/v1"`.
+  pub fn new(domain: &[u8]) -> Self {
+    let mut transcript = Self { bytes: Vec::new() };
+    transcript.append_bytes(domain);
+    transcript
+  }
+
+  /// Appends `bytes` as a single component.
+  pub fn append_bytes(&mut self, bytes: &[u8]) {
+    let len = u32::try_from(bytes.len()).expect("transcript component too long");
+    self.bytes.extend_from_slice(&len.to_be_bytes());
+    self.bytes.extend_from_slice(bytes);
+  }
+
+  /// Appends the family and parameters of `G::descriptor()`, binding the challenges to the group
+  /// instance so that a proof made over one group is not checked against challenges valid in
+  /// another.
+  pub fn append_descriptor<G: Group>(&mut self) {
+    let GroupDescriptor { family, params } = G::descriptor();
+    self.append_bytes(family.as_bytes());
+    self.append_bytes(&params);
+  }
+
+  /// Appends the canonical encoding `G::to_bytes` of `elem`.
+  pub fn append_elem<G: Group>(&mut self, elem: &G::Elem) {
+    self.append_bytes(&G::to_bytes(elem));
+  }
+
+  /// Appends `x` as a sign byte followed by its big-endian magnitude.
+  pub fn append_bigint(&mut self, x: &Integer) {
+    self.append_bytes(&int_to_bytes(x));
+  }
+
+  /// Returns a `lambda`-bit prime derived from the transcript, for use as a challenge.
+  ///
+  /// Panics if `lambda` is not between 2 and 256.
+  pub fn challenge_prime(&self, lambda: u32) -> Integer {
+    assert!(
+      (2..=256).contains(&lambda),
+      "lambda must be between 2 and 256"
+    );
+    transcript_to_prime(&self.bytes, lambda)
+  }
+
+  /// Returns a 256-bit integer derived from the transcript, for use as a non-prime challenge.
+  pub fn challenge_scalar(&self) -> Integer {
+    blake2b(&self.bytes)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The different types of errors when decoding a proof from bytes.
+pub enum ProofError {
+  /// The input does not have the exact length of an encoded proof over the group.
+  BadLength,
+
+  /// Some component of the input is not the canonical encoding of a group element.
+  BadElem,
+}
+
+/// Version tag embedded in the `serde` representation of proofs. Bumped whenever that
+/// representation changes, so that proofs serialized by other versions of this crate are rejected
+/// instead of misread.
+#[cfg(feature = "serde")]
+const SERDE_SCHEMA_VERSION: u32 = 1;
+
+/// Encodes `bytes` as a lowercase hex string, for the `serde` representation of proofs.
+#[cfg(feature = "serde")]
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Inverse of `to_hex`. Returns `None` if `s` is not a hex string of even length.
+#[cfg(feature = "serde")]
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+  if !s.len().is_multiple_of(2) || !s.is_ascii() {
+    return None;
+  }
+  (0..s.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+    .collect()
+}
+
+/// Maximum number of challenge primes memoized under the `challenge-cache` feature. The cache is
+/// cleared when full, which bounds its memory without tracking recency.
+#[cfg(feature = "challenge-cache")]
+const CHALLENGE_CACHE_CAPACITY: usize = 1024;
+
+#[cfg(feature = "challenge-cache")]
+lazy_static! {
+  static ref CHALLENGE_CACHE: Mutex<HashMap<[u8; 32], Integer>> = Mutex::new(HashMap::new());
+}
+
+/// Hashes `transcript` to a `lambda`-bit prime, by hashing it together with an incrementing
+/// counter until the output, truncated to `lambda` bits with the top and bottom bits set, is prime.
+///
+/// With the `challenge-cache` feature, primes are memoized by the Blake2b digest of the transcript,
+/// which avoids repeating the prime search when the same statement is proven and verified (or
+/// verified several times) in one process.
+fn transcript_to_prime(transcript: &[u8], lambda: u32) -> Integer {
+  #[cfg(feature = "challenge-cache")]
+  {
+    let key = hash(&Blake2b::default, &(transcript, lambda));
+    if let Some(l) = CHALLENGE_CACHE.lock().unwrap().get(&key) {
+      return l.clone();
+    }
+    let l = find_prime(transcript, lambda);
+    let mut cache = CHALLENGE_CACHE.lock().unwrap();
+    if cache.len() >= CHALLENGE_CACHE_CAPACITY {
+      cache.clear();
+    }
+    cache.insert(key, l.clone());
+    l
+  }
+  #[cfg(not(feature = "challenge-cache"))]
+  find_prime(transcript, lambda)
+}
+
+/// The uncached prime search behind `transcript_to_prime`.
+fn find_prime(transcript: &[u8], lambda: u32) -> Integer {
+  let lambda = lambda as usize;
+  let mut counter = 0_u64;
+  loop {
+    // Lower-endian, as expected by `u256`.
+    let mut candidate = hash(&Blake2b::default, &(transcript, counter));
+    for (i, byte) in candidate.iter_mut().enumerate() {
+      if 8 * i >= lambda {
+        *byte = 0;
+      } else if lambda - 8 * i < 8 {
+        *byte &= (1 << (lambda - 8 * i)) - 1;
+      }
+    }
+    candidate[(lambda - 1) / 8] |= 1 << ((lambda - 1) % 8);
+    candidate[0] |= 1;
+    let candidate = u256(candidate);
+    if primality::is_prob_prime(&candidate) {
+      return Integer::from(candidate);
+    }
+    counter += 1;
+  }
+}
+
+/// Encodes `x` as a sign byte followed by its big-endian magnitude.
+fn int_to_bytes(x: &Integer) -> Vec<u8> {
+  let mut bytes = vec![(*x < 0).into()];
+  bytes.extend(x.to_digits::<u8>(Order::Msf));
+  bytes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::{DummyRsa, ElemFrom, Rsa2048, UnknownOrderGroup};
+  use crate::util::int;
+  use rug::integer::IsPrime;
+
+  fn transcript(components: &[&[u8]]) -> Transcript {
+    let mut transcript = Transcript::new(b"test");
+    for component in components {
+      transcript.append_bytes(component);
+    }
+    transcript
+  }
+
+  #[test]
+  fn test_transcript_injective() {
+    assert_ne!(transcript(&[b"ab", b"c"]), transcript(&[b"a", b"bc"]));
+    assert_ne!(transcript(&[b"", b"a"]), transcript(&[b"a"]));
+    assert_ne!(Transcript::new(b"a"), Transcript::new(b"b"));
+    assert_ne!(Transcript::new(b"ab"), transcript(&[]));
+
+    let mut pos = Transcript::new(b"test");
+    pos.append_bigint(&int(5));
+    let mut neg = Transcript::new(b"test");
+    neg.append_bigint(&int(-5));
+    assert_ne!(pos, neg);
+    assert_ne!(pos.challenge_prime(256), neg.challenge_prime(256));
+    assert_ne!(pos.challenge_scalar(), neg.challenge_scalar());
+
+    let x = Rsa2048::elem(5);
+    let mut elem = Transcript::new(b"test");
+    elem.append_elem::<Rsa2048>(&x);
+    assert_eq!(elem, transcript(&[&Rsa2048::to_bytes(&x)]));
+  }
+
+  #[test]
+  fn test_verifiable() {
+    let base = DummyRsa::unknown_order_elem();
+    let exp = int(1) << 100;
+    let result = DummyRsa::exp(&base, &exp);
+    let proofs: Vec<Box<dyn Verifiable<DummyRsa>>> = vec![
+      Box::new(Poe::<DummyRsa>::prove(&base, &exp, &result)),
+      Box::new(Poke2::<DummyRsa>::prove(&base, &exp, &result)),
+    ];
+    let statements = [
+      Statement::Exp {
+        base: base.clone(),
+        exp: exp.clone(),
+        result: result.clone(),
+      },
+      Statement::KnowledgeOfExp {
+        base: base.clone(),
+        result: result.clone(),
+      },
+    ];
+    for (proof, statement) in proofs.iter().zip(&statements) {
+      assert!(proof.verify(statement));
+    }
+    // Each proof rejects the other kind of statement.
+    for (proof, statement) in proofs.iter().zip(statements.iter().rev()) {
+      assert!(!proof.verify(statement));
+    }
+    assert!(!proofs[0].verify(&Statement::Exp {
+      base: base.clone(),
+      exp: exp + 1,
+      result: result.clone(),
+    }));
+    assert!(!proofs[1].verify(&Statement::KnowledgeOfExp {
+      base: DummyRsa::elem(9),
+      result,
+    }));
+  }
+
+  #[test]
+  fn test_challenge_prime() {
+    let t = transcript(&[b"martian", b"cyborg"]);
+    for &lambda in &[2, 8, 64, 127, 256] {
+      let l = t.challenge_prime(lambda);
+      assert_eq!(l.significant_bits(), lambda);
+      assert!(l.is_probably_prime(30) != IsPrime::No);
+      // The second call is served from the cache when the `challenge-cache` feature is enabled.
+      assert_eq!(t.challenge_prime(lambda), l);
+    }
+    assert_ne!(
+      transcript(&[b"gerbil"]).challenge_prime(256),
+      t.challenge_prime(256)
+    );
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_hex() {
+    assert_eq!(to_hex(&[0, 1, 0xab]), "0001ab");
+    assert_eq!(from_hex("0001ab"), Some(vec![0, 1, 0xab]));
+    assert_eq!(from_hex("0001a"), None);
+    assert_eq!(from_hex("zz"), None);
+  }
+}
@@ -1,48 +1,306 @@
 //! Non-Interactive Proofs of Exponentiation (NI-PoE). See BBF (pages 8 and 42) for details.
+#[cfg(feature = "serde")]
+use super::{from_hex, to_hex, SERDE_SCHEMA_VERSION};
+use super::{ProofError, SecurityLevel, Statement, Transcript, Verifiable};
 use crate::group::Group;
-use crate::hash::hash_to_prime;
-use crate::util::int;
+use crate::util::{int, product_mod};
 use rug::Integer;
+#[cfg(feature = "serde")]
+use serde::de::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+/// Domain label of the Fiat-Shamir transcript.
+const DOMAIN: &[u8] = b"accumulator/poe/v1";
 
 #[allow(non_snake_case)]
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 /// Struct for NI-PoE.
+///
+/// With the `serde` feature, serializes as `{"version": 1, "q": <hex>}`, where `q` is the hex
+/// encoding of `G::to_bytes(Q)`.
 pub struct Poe<G: Group> {
   Q: G::Elem,
 }
 
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PoeWire {
+  version: u32,
+  q: String,
+}
+
+#[cfg(feature = "serde")]
+impl<G: Group> Serialize for Poe<G> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    PoeWire {
+      version: SERDE_SCHEMA_VERSION,
+      q: to_hex(&G::to_bytes(&self.Q)),
+    }
+    .serialize(serializer)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, G: Group> Deserialize<'de> for Poe<G> {
+  #[allow(non_snake_case)]
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let wire = PoeWire::deserialize(deserializer)?;
+    if wire.version != SERDE_SCHEMA_VERSION {
+      return Err(D::Error::custom("unsupported proof schema version"));
+    }
+    let Q = from_hex(&wire.q)
+      .and_then(|bytes| G::from_bytes(&bytes))
+      .ok_or_else(|| D::Error::custom("invalid group element"))?;
+    Ok(Self { Q })
+  }
+}
+
 impl<G: Group> Poe<G> {
+  /// Returns the Fiat-Shamir prime challenge `l` for the statement `base ^ exp = result`, as
+  /// computed by `prove` and `verify`. Exposed so that protocols composing this proof with others
+  /// can bind the challenge into a larger transcript.
+  pub fn challenge(base: &G::Elem, exp: &Integer, result: &G::Elem) -> Integer {
+    Self::challenge_at_level(base, exp, result, SecurityLevel::default())
+  }
+
+  /// Like `challenge`, but for proofs at security level `level`.
+  pub fn challenge_at_level(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    level: SecurityLevel,
+  ) -> Integer {
+    Self::challenge_(base, exp, result, level, &[])
+  }
+
+  /// Derives the challenge, binding `context` if it is nonempty. An empty context leaves the
+  /// transcript unchanged, so that proofs without context keep their challenges.
+  fn challenge_(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    level: SecurityLevel,
+    context: &[u8],
+  ) -> Integer {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_descriptor::<G>();
+    transcript.append_elem::<G>(base);
+    transcript.append_bigint(exp);
+    transcript.append_elem::<G>(result);
+    if !context.is_empty() {
+      transcript.append_bytes(context);
+    }
+    transcript.challenge_prime(level.challenge_bits())
+  }
+
   /// Computes a proof that `base ^ exp` was performed to derive `result`.
   pub fn prove(base: &G::Elem, exp: &Integer, result: &G::Elem) -> Self {
-    let l = hash_to_prime(&(base, exp, result));
-    let q = exp / l;
+    Self::prove_with_quotient_bits(base, exp, result).0
+  }
+
+  /// Like `prove`, but at security level `level`. The proof only verifies via `verify_at_level` at
+  /// the same level.
+  pub fn prove_at_level(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    level: SecurityLevel,
+  ) -> Self {
+    let l = Self::challenge_at_level(base, exp, result, level);
+    Self::prove_with_challenge(base, exp, &l)
+  }
+
+  /// Like `prove`, but also binds `context` into the challenge, e.g. the height and timestamp of
+  /// the block an update belongs to, so that the proof only verifies via `verify_with_context`
+  /// under the same context and cannot be replayed elsewhere. An empty context gives the same proof
+  /// as `prove`.
+  pub fn prove_with_context(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    context: &[u8],
+  ) -> Self {
+    let l = Self::challenge_(base, exp, result, SecurityLevel::default(), context);
+    Self::prove_with_challenge(base, exp, &l)
+  }
+
+  /// Computes the proof for the challenge `l`, for protocols that derive it from their own
+  /// transcript, which must bind the group descriptor, `base`, `exp` and `result`.
+  pub(crate) fn prove_with_challenge(base: &G::Elem, exp: &Integer, l: &Integer) -> Self {
     Self {
-      Q: G::exp(&base, &q),
+      Q: G::exp(base, &int(exp / l)),
     }
   }
 
+  /// Like `prove`, but also returns the bit length of the quotient `q = exp / l`, where `Q = base ^
+  /// q`, for callers that account for the cost or size of proofs.
+  pub fn prove_with_quotient_bits(base: &G::Elem, exp: &Integer, result: &G::Elem) -> (Self, u32) {
+    let l = Self::challenge(base, exp, result);
+    let q = exp / l;
+    let proof = Self {
+      Q: G::exp(base, &q),
+    };
+    (proof, q.significant_bits())
+  }
+
   /// Verifies that `base ^ exp = result` using the given proof to avoid computation.
+  ///
+  /// The challenge `l` is derived from `exp` itself, so a proof for `exp` does not verify against
+  /// any other exponent, including those congruent to `exp` mod `l`.
   pub fn verify(base: &G::Elem, exp: &Integer, result: &G::Elem, proof: &Self) -> bool {
-    let l = hash_to_prime(&(base, exp, result));
-    let r = int(exp % &l);
+    Self::verify_at_level(base, exp, result, proof, SecurityLevel::default())
+  }
+
+  /// Like `verify`, but for a proof made by `prove_at_level` at security level `level`.
+  pub fn verify_at_level(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    proof: &Self,
+    level: SecurityLevel,
+  ) -> bool {
+    let l = Self::challenge_at_level(base, exp, result, level);
+    Self::verify_with_challenge(base, exp, result, proof, &l)
+  }
+
+  /// Like `verify`, for `exp` given as the product of the nonnegative `factors`, e.g. the primes of
+  /// a batch of elements. The remainder `r = exp mod l` is computed from the factors via
+  /// `product_mod`, so `exp` is only built to derive the challenge and never divided.
+  pub fn verify_product(
+    base: &G::Elem,
+    factors: &[Integer],
+    result: &G::Elem,
+    proof: &Self,
+  ) -> bool {
+    let exp: Integer = factors.iter().product();
+    let l = Self::challenge(base, &exp, result);
+    Self::verify_remainder(base, &product_mod(factors, &l), result, proof, &l)
+  }
+
+  /// Verifies a proof from `prove_with_context` made under `context`.
+  pub fn verify_with_context(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    proof: &Self,
+    context: &[u8],
+  ) -> bool {
+    let l = Self::challenge_(base, exp, result, SecurityLevel::default(), context);
+    Self::verify_with_challenge(base, exp, result, proof, &l)
+  }
+
+  /// Verifies a proof from `prove_with_challenge` for the challenge `l`.
+  pub(crate) fn verify_with_challenge(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    proof: &Self,
+    l: &Integer,
+  ) -> bool {
+    Self::verify_remainder(base, &int(exp % l), result, proof, l)
+  }
+
+  /// Checks `Q ^ l * base ^ r = result` for the remainder `r = exp mod l`.
+  fn verify_remainder(
+    base: &G::Elem,
+    r: &Integer,
+    result: &G::Elem,
+    proof: &Self,
+    l: &Integer,
+  ) -> bool {
     // w = Q^l * u^r
-    let w = G::op(&G::exp(&proof.Q, &l), &G::exp(&base, &r));
+    let w = G::op(&G::exp(&proof.Q, l), &G::exp(base, r));
     w == *result
   }
+
+  /// Returns the quotient commitment `Q = base ^ (exp / l)`.
+  pub fn q(&self) -> &G::Elem {
+    &self.Q
+  }
+
+  /// Returns the byte encoding of the proof, i.e. `G::to_bytes` of `Q`.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    G::to_bytes(&self.Q)
+  }
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. Returns `None` if `bytes` is
+  /// malformed; see the `TryFrom<&[u8]>` impl for the reason.
+  pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    Self::try_from(bytes).ok()
+  }
+}
+
+impl<G: Group> Verifiable<G> for Poe<G> {
+  fn verify(&self, statement: &Statement<G>) -> bool {
+    match statement {
+      Statement::Exp { base, exp, result } => Poe::verify(base, exp, result, self),
+      _ => false,
+    }
+  }
+}
+
+impl<G: Group> TryFrom<&[u8]> for Poe<G> {
+  type Error = ProofError;
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. The length of `bytes` is checked
+  /// against the element size of `G` before anything is decoded.
+  #[allow(non_snake_case)]
+  fn try_from(bytes: &[u8]) -> Result<Self, ProofError> {
+    if bytes.len() != G::ELEM_BYTES {
+      return Err(ProofError::BadLength);
+    }
+    let Q = G::from_bytes(bytes).ok_or(ProofError::BadElem)?;
+    Ok(Self { Q })
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::group::{ElemFrom, Rsa2048, UnknownOrderGroup};
+  use crate::group::{DummyRsa, DummyRsa2, DummyRsaElem, ElemFrom, Rsa2048, UnknownOrderGroup};
   use crate::util::int;
 
+  /// A proof transplanted byte-for-byte into another group with the same element encoding is
+  /// checked against that group's challenge.
+  #[test]
+  fn test_poe_bound_to_group() {
+    let transplant = |x: &DummyRsaElem| DummyRsa2::from_bytes(&DummyRsa::to_bytes(x)).unwrap();
+    let base = DummyRsa::elem(4);
+    let exp = int(1) << 300;
+    let result = DummyRsa::exp(&base, &exp);
+    let (base_2, result_2) = (transplant(&base), transplant(&result));
+    assert!(
+      Poe::<DummyRsa>::challenge(&base, &exp, &result)
+        != Poe::<DummyRsa2>::challenge(&base_2, &exp, &result_2)
+    );
+    let proof = Poe::<DummyRsa>::prove(&base, &exp, &result);
+    assert!(Poe::verify(&base, &exp, &result, &proof));
+    let proof_2 = Poe::<DummyRsa2>::from_bytes(&proof.to_bytes()).unwrap();
+    assert!(!Poe::verify(&base_2, &exp, &result_2, &proof_2));
+  }
+
+  #[test]
+  fn test_poe_verify_product() {
+    let base = Rsa2048::unknown_order_elem();
+    let factors = [int(1) << 200, int(65537), int(3)];
+    let exp: Integer = factors.iter().product();
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    assert!(Poe::verify_product(&base, &factors, &result, &proof));
+    assert!(!Poe::verify_product(&base, &factors[..2], &result, &proof));
+    let wrong_result = Rsa2048::op(&result, &base);
+    assert!(!Poe::verify_product(&base, &factors, &wrong_result, &proof));
+  }
+
   #[test]
   fn test_poe_small_exp() {
-    // 2^20 = 1048576
+    // 4^20 = 2^40 = 1099511627776
     let base = Rsa2048::unknown_order_elem();
     let exp = int(20);
-    let result = Rsa2048::elem(1_048_576);
+    let result = Rsa2048::elem(1_099_511_627_776u64);
     let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
     assert!(Poe::verify(&base, &exp, &result, &proof));
     assert!(
@@ -52,9 +310,9 @@ mod tests {
         }
     );
 
-    // 2^35 = 34359738368
+    // 4^35 = 2^70
     let exp_2 = int(35);
-    let result_2 = Rsa2048::elem(34_359_738_368u64);
+    let result_2 = Rsa2048::elem(int(1) << 70);
     let proof_2 = Poe::<Rsa2048>::prove(&base, &exp_2, &result_2);
     assert!(Poe::verify(&base, &exp_2, &result_2, &proof_2));
     assert!(
@@ -64,4 +322,141 @@ mod tests {
         }
     );
   }
+
+  #[test]
+  fn test_poe_quotient_bits() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let (proof, q_bits) = Poe::<Rsa2048>::prove_with_quotient_bits(&base, &exp, &result);
+    let l = Poe::<Rsa2048>::challenge(&base, &exp, &result);
+    assert_eq!(q_bits, int(&exp / &l).significant_bits());
+    // `2 ^ 255 < l < 2 ^ 256`, so `2 ^ 44 < q < 2 ^ 45`.
+    assert_eq!(q_bits, 45);
+    assert!(proof == Poe::prove(&base, &exp, &result));
+
+    let (_, q_bits) = Poe::<Rsa2048>::prove_with_quotient_bits(&base, &int(20), &result);
+    assert_eq!(q_bits, 0);
+  }
+
+  #[test]
+  fn test_poe_exp_binding() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let l = Poe::<Rsa2048>::challenge(&base, &exp, &result);
+    // Same remainder mod `l`, so the check `Q ^ l * base ^ r = result` would pass if `l` were
+    // reused.
+    let exp_2 = int(&exp + &l);
+    assert!(Poe::verify(&base, &exp, &result, &proof));
+    assert!(!Poe::verify(&base, &exp_2, &result, &proof));
+  }
+
+  #[test]
+  fn test_poe_security_levels() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    for &(level, bits) in &[(SecurityLevel::Bits128, 128), (SecurityLevel::Bits256, 256)] {
+      let l = Poe::<Rsa2048>::challenge_at_level(&base, &exp, &result, level);
+      assert_eq!(l.significant_bits(), bits);
+      let proof = Poe::<Rsa2048>::prove_at_level(&base, &exp, &result, level);
+      assert!(Poe::verify_at_level(&base, &exp, &result, &proof, level));
+    }
+    let proof = Poe::<Rsa2048>::prove_at_level(&base, &exp, &result, SecurityLevel::Bits128);
+    assert!(!Poe::verify(&base, &exp, &result, &proof));
+    assert!(
+      Poe::prove_at_level(&base, &exp, &result, SecurityLevel::default())
+        == Poe::<Rsa2048>::prove(&base, &exp, &result)
+    );
+  }
+
+  #[test]
+  fn test_poe_context() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove_with_context(&base, &exp, &result, b"height 1");
+    assert!(Poe::verify_with_context(
+      &base,
+      &exp,
+      &result,
+      &proof,
+      b"height 1"
+    ));
+    assert!(!Poe::verify_with_context(
+      &base,
+      &exp,
+      &result,
+      &proof,
+      b"height 2"
+    ));
+    assert!(!Poe::verify(&base, &exp, &result, &proof));
+    assert!(
+      Poe::prove_with_context(&base, &exp, &result, &[])
+        == Poe::<Rsa2048>::prove(&base, &exp, &result)
+    );
+  }
+
+  #[test]
+  fn test_poe_accessors() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let l = Poe::<Rsa2048>::challenge(&base, &exp, &result);
+    assert!(*proof.q() == Rsa2048::exp(&base, &int(&exp / &l)));
+  }
+
+  #[test]
+  fn test_poe_challenge() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let l = Poe::<Rsa2048>::challenge(&base, &exp, &result);
+    let r = int(&exp % &l);
+    assert!(Rsa2048::op(&Rsa2048::exp(&proof.Q, &l), &Rsa2048::exp(&base, &r)) == result);
+    assert!(l != Poe::<Rsa2048>::challenge(&base, &int(&exp + 1), &result));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_poe_serde() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let json = serde_json::to_value(&proof).unwrap();
+    let keys = json.as_object().unwrap().keys().collect::<Vec<_>>();
+    assert_eq!(keys, ["q", "version"]);
+    assert_eq!(json["version"], 1);
+    assert!(serde_json::from_value::<Poe<Rsa2048>>(json).unwrap() == proof);
+  }
+
+  #[test]
+  fn test_poe_bytes() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    assert!(Poe::from_bytes(&proof.to_bytes()) == Some(proof));
+  }
+
+  #[test]
+  fn test_poe_try_from_bytes() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poe::<Rsa2048>::prove(&base, &exp, &result);
+    let mut bytes = proof.to_bytes();
+    assert!(Poe::try_from(&bytes[..]) == Ok(proof));
+    assert!(Poe::<Rsa2048>::try_from(&bytes[1..]) == Err(ProofError::BadLength));
+    assert!(Poe::<Rsa2048>::try_from(&[][..]) == Err(ProofError::BadLength));
+    bytes.push(0);
+    assert!(Poe::<Rsa2048>::try_from(&bytes[..]) == Err(ProofError::BadLength));
+    let zeros = vec![0; bytes.len() - 1];
+    assert!(Poe::<Rsa2048>::try_from(&zeros[..]) == Err(ProofError::BadElem));
+  }
 }
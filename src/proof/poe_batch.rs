@@ -0,0 +1,155 @@
+//! Aggregated NI-PoE for several statements `base_i ^ exp_i = result_i`, each with its own base.
+//! The statements are combined into `prod(base_i ^ (gamma_i * exp_i)) = prod(result_i ^ gamma_i)`
+//! by coefficients `gamma_i` derived from all of them, which is then proven with a single quotient
+//! commitment as in `Poe`.
+use super::{SecurityLevel, Transcript};
+use crate::group::Group;
+use crate::util::int;
+use rug::Integer;
+
+/// Domain label of the Fiat-Shamir transcript for the combination coefficients and the challenge.
+const DOMAIN: &[u8] = b"accumulator/poe-batch/v1";
+
+#[allow(non_snake_case)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+/// Struct for an aggregated NI-PoE over statements `bases[i] ^ exps[i] = results[i]`.
+///
+/// The proof has the size of a single `Poe`. A batch in which some `bases[i] ^ exps[i] !=
+/// results[i]` only verifies with negligible probability. Verifying takes two exponentiations by
+/// challenge-sized exponents per statement, rather than one by `exps[i]`.
+pub struct PoeBatch<G: Group> {
+  Q: G::Elem,
+}
+
+impl<G: Group> PoeBatch<G> {
+  /// Returns the combination coefficients `gamma_i` and the prime challenge `l`, all bound to every
+  /// statement.
+  fn challenges(
+    bases: &[G::Elem],
+    exps: &[Integer],
+    results: &[G::Elem],
+  ) -> (Vec<Integer>, Integer) {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_descriptor::<G>();
+    for ((base, exp), result) in bases.iter().zip(exps).zip(results) {
+      transcript.append_elem::<G>(base);
+      transcript.append_bigint(exp);
+      transcript.append_elem::<G>(result);
+    }
+    let coefficients = (0..bases.len())
+      .map(|i| {
+        let mut transcript = transcript.clone();
+        transcript.append_bigint(&Integer::from(i));
+        transcript.challenge_scalar()
+      })
+      .collect();
+    (
+      coefficients,
+      transcript.challenge_prime(SecurityLevel::default().challenge_bits()),
+    )
+  }
+
+  /// Panics unless `bases`, `exps` and `results` have the same length.
+  fn check_lengths(bases: &[G::Elem], exps: &[Integer], results: &[G::Elem]) {
+    assert!(
+      bases.len() == exps.len() && exps.len() == results.len(),
+      "bases, exps and results must have the same length"
+    );
+  }
+
+  /// Computes a proof that `bases[i] ^ exps[i] = results[i]` for every `i`.
+  ///
+  /// Panics if `bases`, `exps` and `results` have different lengths.
+  #[allow(non_snake_case)]
+  pub fn prove(bases: &[G::Elem], exps: &[Integer], results: &[G::Elem]) -> Self {
+    Self::check_lengths(bases, exps, results);
+    let (coefficients, l) = Self::challenges(bases, exps, results);
+    // Q = prod(base_i ^ (gamma_i * exp_i / l))
+    let Q = bases
+      .iter()
+      .zip(exps)
+      .zip(&coefficients)
+      .fold(G::id(), |acc, ((base, exp), gamma)| {
+        G::op(&acc, &G::exp(base, &(int(exp * gamma) / &l)))
+      });
+    Self { Q }
+  }
+
+  /// Verifies that `bases[i] ^ exps[i] = results[i]` for every `i`. Returns `false` if the slices
+  /// have different lengths.
+  pub fn verify(bases: &[G::Elem], exps: &[Integer], results: &[G::Elem], proof: &Self) -> bool {
+    if bases.len() != exps.len() || exps.len() != results.len() {
+      return false;
+    }
+    let (coefficients, l) = Self::challenges(bases, exps, results);
+    // Q^l * prod(base_i ^ (gamma_i * exp_i mod l)) = prod(result_i ^ gamma_i)
+    let lhs = bases
+      .iter()
+      .zip(exps)
+      .zip(&coefficients)
+      .fold(G::exp(&proof.Q, &l), |acc, ((base, exp), gamma)| {
+        G::op(&acc, &G::exp(base, &(int(exp * gamma) % &l)))
+      });
+    let rhs = results
+      .iter()
+      .zip(&coefficients)
+      .fold(G::id(), |acc, (result, gamma)| {
+        G::op(&acc, &G::exp(result, gamma))
+      });
+    lhs == rhs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::{ElemFrom, Rsa2048, UnknownOrderGroup};
+
+  #[test]
+  fn test_poe_batch() {
+    let bases = [
+      Rsa2048::elem(2),
+      Rsa2048::unknown_order_elem(),
+      Rsa2048::elem(12345),
+    ];
+    let exps = [int(1) << 300, int(-5), int(987_654_321)];
+    let results = bases
+      .iter()
+      .zip(&exps)
+      .map(|(base, exp)| Rsa2048::exp(base, exp))
+      .collect::<Vec<_>>();
+    let proof = PoeBatch::<Rsa2048>::prove(&bases, &exps, &results);
+    assert!(PoeBatch::verify(&bases, &exps, &results, &proof));
+
+    let mut wrong_results = results.clone();
+    wrong_results[1] = Rsa2048::exp(&bases[1], &int(-4));
+    assert!(!PoeBatch::verify(&bases, &exps, &wrong_results, &proof));
+    let wrong_proof = PoeBatch::<Rsa2048>::prove(&bases, &exps, &wrong_results);
+    assert!(!PoeBatch::verify(
+      &bases,
+      &exps,
+      &wrong_results,
+      &wrong_proof
+    ));
+    let mut wrong_exps = exps.clone();
+    wrong_exps[2] += 1;
+    assert!(!PoeBatch::verify(&bases, &wrong_exps, &results, &proof));
+    assert!(!PoeBatch::verify(
+      &bases[..2],
+      &exps[..2],
+      &results[..2],
+      &proof
+    ));
+  }
+
+  #[test]
+  #[should_panic(expected = "same length")]
+  fn test_poe_batch_length_mismatch() {
+    let base = Rsa2048::elem(2);
+    PoeBatch::<Rsa2048>::prove(
+      &[base.clone(), base],
+      &[int(1), int(2)],
+      &[Rsa2048::elem(4)],
+    );
+  }
+}
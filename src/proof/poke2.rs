@@ -1,73 +1,397 @@
 //! Non-Interactive Proofs of Knowledge of Exponent (NI-PoKE2). See BBF (pages 10 and 42) for
 //! details.
-use crate::group::UnknownOrderGroup;
-use crate::hash::{blake2b, hash_to_prime};
+#[cfg(feature = "serde")]
+use super::{from_hex, to_hex, SERDE_SCHEMA_VERSION};
+use super::{ProofError, SecurityLevel, Statement, Transcript, Verifiable, CHALLENGE_BITS};
+use crate::group::{multi_exp_simultaneous, UnknownOrderGroup};
+use crate::util::{int, to_fixed_be_bytes};
+use rug::integer::Order;
 use rug::Integer;
+#[cfg(feature = "serde")]
+use serde::de::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+/// Domain label of the Fiat-Shamir transcript.
+const DOMAIN: &[u8] = b"accumulator/poke2/v1";
+
+/// Byte length of the encoded remainder `r`, which is reduced mod a prime challenge of at most
+/// `CHALLENGE_BITS` bits.
+const R_BYTES: usize = (CHALLENGE_BITS / 8) as usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The reasons `Poke2::verify_detailed` can reject a proof.
+///
+/// There is no variant for a wrong challenge: `l` and `alpha` are recomputed from the statement and
+/// `z` rather than carried in the proof, so a tampered `z` shows up as one of the other errors.
+pub enum Poke2Error {
+  /// The remainder `r` is not in `[0, l)`, so it was not reduced mod the challenge `l`.
+  RemainderOutOfRange,
+
+  /// The verification equation `Q ^ l * (base * g ^ alpha) ^ r = result * z ^ alpha` does not hold.
+  EquationMismatch,
+}
 
 #[allow(non_snake_case)]
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 /// Struct for NI-PoKE2.
+///
+/// With the `serde` feature, serializes as
+/// `{"version": 1, "z": <hex>, "q": <hex>, "remainder": <hex>}`, where `z` and `q` are the hex
+/// encodings of `G::to_bytes` of `z` and `Q`, and `remainder` is `r` as a 32-byte big-endian hex
+/// string.
 pub struct Poke2<G: UnknownOrderGroup> {
   z: G::Elem,
   Q: G::Elem,
   r: Integer,
 }
 
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Poke2Wire {
+  version: u32,
+  z: String,
+  q: String,
+  remainder: String,
+}
+
+#[cfg(feature = "serde")]
+impl<G: UnknownOrderGroup> Serialize for Poke2<G> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    Poke2Wire {
+      version: SERDE_SCHEMA_VERSION,
+      z: to_hex(&G::to_bytes(&self.z)),
+      q: to_hex(&G::to_bytes(&self.Q)),
+      remainder: to_hex(&to_fixed_be_bytes(&self.r, R_BYTES)),
+    }
+    .serialize(serializer)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, G: UnknownOrderGroup> Deserialize<'de> for Poke2<G> {
+  #[allow(non_snake_case)]
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let wire = Poke2Wire::deserialize(deserializer)?;
+    if wire.version != SERDE_SCHEMA_VERSION {
+      return Err(D::Error::custom("unsupported proof schema version"));
+    }
+    let elem = |hex: &str| {
+      from_hex(hex)
+        .and_then(|bytes| G::from_bytes(&bytes))
+        .ok_or_else(|| D::Error::custom("invalid group element"))
+    };
+    let z = elem(&wire.z)?;
+    let Q = elem(&wire.q)?;
+    let r = match from_hex(&wire.remainder) {
+      Some(ref bytes) if bytes.len() == R_BYTES => Integer::from_digits(bytes, Order::Msf),
+      _ => return Err(D::Error::custom("invalid remainder")),
+    };
+    Ok(Self { z, Q, r })
+  }
+}
+
 impl<G: UnknownOrderGroup> Poke2<G> {
+  /// Returns the Fiat-Shamir prime challenge `l` for the statement `base ^ exp = result` and the
+  /// commitment `z = g ^ exp`, as computed by `prove` and `verify`. Exposed so that protocols
+  /// composing this proof with others can bind the challenge into a larger transcript.
+  pub fn challenge(base: &G::Elem, result: &G::Elem, z: &G::Elem) -> Integer {
+    Self::challenge_at_level(base, result, z, SecurityLevel::default())
+  }
+
+  /// Like `challenge`, but for proofs at security level `level`.
+  pub fn challenge_at_level(
+    base: &G::Elem,
+    result: &G::Elem,
+    z: &G::Elem,
+    level: SecurityLevel,
+  ) -> Integer {
+    Self::transcript(base, result, z).challenge_prime(level.challenge_bits())
+  }
+
+  /// Returns the challenges `(l, alpha)` that `verify` derives for a proof with commitment `z`.
+  ///
+  /// This method is only public for benchmarking. You should not need to use it.
+  pub fn challenges(base: &G::Elem, result: &G::Elem, z: &G::Elem) -> (Integer, Integer) {
+    let transcript = Self::transcript(base, result, z);
+    let l = transcript.challenge_prime(SecurityLevel::default().challenge_bits());
+    let alpha = Self::alpha_(transcript, &l);
+    (l, alpha)
+  }
+
+  /// Returns the transcript of the statement alone, which `transcript` extends with `z`.
+  fn statement_transcript(base: &G::Elem, result: &G::Elem) -> Transcript {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_descriptor::<G>();
+    transcript.append_elem::<G>(base);
+    transcript.append_elem::<G>(result);
+    transcript
+  }
+
+  /// Returns the transcript of the statement and the commitment `z`, from which both `l` and
+  /// `alpha` are derived.
+  fn transcript(base: &G::Elem, result: &G::Elem, z: &G::Elem) -> Transcript {
+    let mut transcript = Self::statement_transcript(base, result);
+    transcript.append_elem::<G>(z);
+    transcript
+  }
+
+  /// Returns the Fiat-Shamir scalar `alpha`, which is derived from the same transcript as the
+  /// challenge `l` with `l` appended.
+  fn alpha(base: &G::Elem, result: &G::Elem, z: &G::Elem, l: &Integer) -> Integer {
+    Self::alpha_(Self::transcript(base, result, z), l)
+  }
+
+  /// Like `alpha`, given the transcript of the statement and `z`.
+  fn alpha_(mut transcript: Transcript, l: &Integer) -> Integer {
+    transcript.append_bigint(l);
+    transcript.challenge_scalar()
+  }
+
+  /// Returns a verifier for proofs of the statement `base ^ exp = result` for some `exp`, which
+  /// serializes the statement into the transcript once rather than per proof. Useful when several
+  /// candidate proofs of one statement are checked.
+  pub fn verifier<'a>(base: &'a G::Elem, result: &'a G::Elem) -> Poke2Verifier<'a, G> {
+    Poke2Verifier {
+      base,
+      result,
+      prefix: Self::statement_transcript(base, result),
+    }
+  }
+
   /// Computes a proof that you know `exp` s.t. `base ^ exp = result`.
+  ///
+  /// Proving is deterministic: the challenges are derived from the statement alone, so identical
+  /// inputs yield byte-identical proofs.
+  ///
+  /// In debug builds, panics if `base ^ exp != result`, since the resulting proof would never
+  /// verify.
   pub fn prove(base: &G::Elem, exp: &Integer, result: &G::Elem) -> Self {
+    Self::prove_at_level(base, exp, result, SecurityLevel::default())
+  }
+
+  /// Like `prove`, but at security level `level`. The proof only verifies via `verify_at_level` at
+  /// the same level.
+  pub fn prove_at_level(
+    base: &G::Elem,
+    exp: &Integer,
+    result: &G::Elem,
+    level: SecurityLevel,
+  ) -> Self {
+    debug_assert_eq!(
+      G::exp(base, exp),
+      *result,
+      "Poke2::prove called with result != base ^ exp"
+    );
+    let z = Self::commitment(exp);
+    let l = Self::challenge_at_level(base, result, &z, level);
+    let alpha = Self::alpha(base, result, &z, &l);
+    Self::prove_with_challenges(base, exp, z, &l, &alpha)
+  }
+
+  /// Returns the commitment `z = g ^ exp` of a proof for `exp`.
+  pub(crate) fn commitment(exp: &Integer) -> G::Elem {
+    G::exp_unknown_order_elem(exp)
+  }
+
+  /// Computes the proof with commitment `z` for the challenges `l` and `alpha`, for protocols that
+  /// derive them from their own transcript, which must bind the group descriptor, `base`, `result`
+  /// and `z`, and in the case of `alpha` also `l`.
+  pub(crate) fn prove_with_challenges(
+    base: &G::Elem,
+    exp: &Integer,
+    z: G::Elem,
+    l: &Integer,
+    alpha: &Integer,
+  ) -> Self {
     let g = G::unknown_order_elem();
-    let z = G::exp(&g, exp);
-    let l = hash_to_prime(&(base, result, &z));
-    let alpha = blake2b(&(base, result, &z, &l));
-    let (q, r) = <(Integer, Integer)>::from(exp.div_rem_euc_ref(&l));
+    let (q, r) = <(Integer, Integer)>::from(exp.div_rem_euc_ref(l));
     #[allow(non_snake_case)]
-    let Q = G::exp(&G::op(&base, &G::exp(&g, &alpha)), &q);
+    let Q = G::exp(&G::op(base, &G::exp(&g, alpha)), &q);
     Self { z, Q, r }
   }
 
-  /// Verifies that the prover knows `exp` s.t. `base ^ exp = result`.
+  /// Verifies a proof from `prove_with_challenges` for the challenges `l` and `alpha`, i.e. the
+  /// group operations of `verify` without the hashing.
+  ///
+  /// This method is only public for benchmarking. You should not need to use it.
   #[allow(non_snake_case)]
-  pub fn verify(base: &G::Elem, result: &G::Elem, Self { z, Q, r }: &Self) -> bool {
+  pub fn verify_with_challenges(
+    base: &G::Elem,
+    result: &G::Elem,
+    Self { z, Q, r }: &Self,
+    l: &Integer,
+    alpha: &Integer,
+  ) -> Result<(), Poke2Error> {
+    if *r < 0 || r >= l {
+      return Err(Poke2Error::RemainderOutOfRange);
+    }
     let g = G::unknown_order_elem();
-    let l = hash_to_prime(&(base, result, &z));
-    let alpha = blake2b(&(base, result, &z, &l));
-    let lhs = G::op(
-      &G::exp(Q, &l),
-      &G::exp(&G::op(&base, &G::exp(&g, &alpha)), &r),
+    // Q ^ l * (base * g ^ alpha) ^ r = result * z ^ alpha, checked as
+    // Q ^ l * (base * g ^ alpha) ^ r * z ^ (-alpha) * result ^ (-1) = 1 with one shared chain of
+    // squarings.
+    let check = multi_exp_simultaneous::<G>(
+      &[
+        Q.clone(),
+        G::op(base, &G::exp(&g, alpha)),
+        z.clone(),
+        result.clone(),
+      ],
+      &[l.clone(), r.clone(), int(-alpha), int(-1)],
     );
-    let rhs = G::op(result, &G::exp(&z, &alpha));
-    lhs == rhs
+    if !G::is_id(&check) {
+      return Err(Poke2Error::EquationMismatch);
+    }
+    Ok(())
+  }
+
+  /// Verifies that the prover knows `exp` s.t. `base ^ exp = result`.
+  pub fn verify(base: &G::Elem, result: &G::Elem, proof: &Self) -> bool {
+    Self::verify_at_level(base, result, proof, SecurityLevel::default())
+  }
+
+  /// Like `verify`, but for a proof made by `prove_at_level` at security level `level`.
+  pub fn verify_at_level(
+    base: &G::Elem,
+    result: &G::Elem,
+    proof: &Self,
+    level: SecurityLevel,
+  ) -> bool {
+    Self::verify_detailed_at_level(base, result, proof, level).is_ok()
+  }
+
+  /// Like `verify`, but returns the reason a proof is rejected, for diagnostics.
+  pub fn verify_detailed(base: &G::Elem, result: &G::Elem, proof: &Self) -> Result<(), Poke2Error> {
+    Self::verify_detailed_at_level(base, result, proof, SecurityLevel::default())
+  }
+
+  /// Like `verify_detailed`, but for a proof made by `prove_at_level` at security level `level`.
+  pub fn verify_detailed_at_level(
+    base: &G::Elem,
+    result: &G::Elem,
+    proof: &Self,
+    level: SecurityLevel,
+  ) -> Result<(), Poke2Error> {
+    Self::verifier(base, result).verify_detailed_at_level(proof, level)
+  }
+
+  /// Returns the commitment `z = g ^ exp`, where `g` is `G::unknown_order_elem()`.
+  pub fn z(&self) -> &G::Elem {
+    &self.z
+  }
+
+  /// Returns the quotient commitment `Q = (base * g ^ alpha) ^ (exp / l)`.
+  pub fn q(&self) -> &G::Elem {
+    &self.Q
+  }
+
+  /// Returns the remainder `r = exp mod l`.
+  pub fn r(&self) -> &Integer {
+    &self.r
+  }
+
+  /// Returns the byte encoding of the proof: `z`, `Q` and `r` in that order, where the elements are
+  /// encoded via `G::to_bytes` and `r` is a 32-byte big-endian integer.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = G::to_bytes(&self.z);
+    bytes.extend(G::to_bytes(&self.Q));
+    bytes.extend(to_fixed_be_bytes(&self.r, R_BYTES));
+    bytes
+  }
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. Returns `None` if `bytes` is
+  /// malformed; see the `TryFrom<&[u8]>` impl for the reason.
+  pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    Self::try_from(bytes).ok()
+  }
+}
+
+#[derive(Clone, Debug)]
+/// Verifies `Poke2` proofs of a fixed statement. See `Poke2::verifier`.
+pub struct Poke2Verifier<'a, G: UnknownOrderGroup> {
+  base: &'a G::Elem,
+  result: &'a G::Elem,
+  prefix: Transcript,
+}
+
+impl<'a, G: UnknownOrderGroup> Poke2Verifier<'a, G> {
+  /// Like `Poke2::verify` for the statement of this verifier.
+  pub fn verify(&self, proof: &Poke2<G>) -> bool {
+    self
+      .verify_detailed_at_level(proof, SecurityLevel::default())
+      .is_ok()
+  }
+
+  /// Like `Poke2::verify_detailed_at_level` for the statement of this verifier.
+  pub fn verify_detailed_at_level(
+    &self,
+    proof: &Poke2<G>,
+    level: SecurityLevel,
+  ) -> Result<(), Poke2Error> {
+    let mut transcript = self.prefix.clone();
+    transcript.append_elem::<G>(&proof.z);
+    let l = transcript.challenge_prime(level.challenge_bits());
+    let alpha = Poke2::<G>::alpha_(transcript, &l);
+    Poke2::verify_with_challenges(self.base, self.result, proof, &l, &alpha)
+  }
+}
+
+impl<G: UnknownOrderGroup> Verifiable<G> for Poke2<G> {
+  fn verify(&self, statement: &Statement<G>) -> bool {
+    match statement {
+      Statement::KnowledgeOfExp { base, result } => Poke2::verify(base, result, self),
+      _ => false,
+    }
+  }
+}
+
+impl<G: UnknownOrderGroup> TryFrom<&[u8]> for Poke2<G> {
+  type Error = ProofError;
+
+  /// Decodes a proof from the encoding produced by `to_bytes`. The length of `bytes` is checked
+  /// against the element size of `G` and the width of `r` before anything is decoded.
+  #[allow(non_snake_case)]
+  fn try_from(bytes: &[u8]) -> Result<Self, ProofError> {
+    let elem_len = G::ELEM_BYTES;
+    if bytes.len() != 2 * elem_len + R_BYTES {
+      return Err(ProofError::BadLength);
+    }
+    let z = G::from_bytes(&bytes[..elem_len]).ok_or(ProofError::BadElem)?;
+    let Q = G::from_bytes(&bytes[elem_len..2 * elem_len]).ok_or(ProofError::BadElem)?;
+    let r = Integer::from_digits(&bytes[2 * elem_len..], Order::Msf);
+    Ok(Self { z, Q, r })
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::group::{ElemFrom, Group, Rsa2048};
+  use crate::group::{DummyRsa, DummyRsa2, DummyRsaElem, ElemFrom, Group, Rsa2048};
   use crate::util::int;
 
   #[test]
   fn test_poke2() {
-    // 2^20 = 1048576
+    // 4^20 = 2^40 = 1099511627776
     let base = Rsa2048::unknown_order_elem();
     let exp = int(20);
-    let result = Rsa2048::elem(1_048_576);
+    let result = Rsa2048::elem(1_099_511_627_776u64);
     let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
     assert!(Poke2::verify(&base, &result, &proof));
     // Must compare entire structs since elements `z`, `Q`, and `r` are private.
     assert!(
       proof
         == Poke2 {
-          z: Rsa2048::elem(1_048_576),
+          z: Rsa2048::elem(1_099_511_627_776u64),
           Q: Rsa2048::elem(1),
           r: int(20)
         }
     );
 
-    // 2^35 = 34359738368
+    // 4^35 = 2^70
     let exp_2 = int(35);
-    let result_2 = Rsa2048::elem(34_359_738_368u64);
+    let result_2 = Rsa2048::elem(int(1) << 70);
     let proof_2 = Poke2::<Rsa2048>::prove(&base, &exp_2, &result_2);
     assert!(Poke2::verify(&base, &result_2, &proof_2));
     // Cannot verify wrong base/exp/result triple with wrong pair.
@@ -75,13 +399,216 @@ mod tests {
     assert!(
       proof_2
         == Poke2 {
-          z: Rsa2048::elem(34_359_738_368u64),
+          z: Rsa2048::elem(int(1) << 70),
           Q: Rsa2048::elem(1),
           r: int(35)
         }
     );
   }
 
+  #[test]
+  fn test_poke2_accessors() {
+    let base = Rsa2048::elem(2);
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let g = Rsa2048::unknown_order_elem();
+    assert!(*proof.z() == Rsa2048::exp(&g, &exp));
+    let l = Poke2::<Rsa2048>::challenge(&base, &result, proof.z());
+    let alpha = Poke2::<Rsa2048>::alpha(&base, &result, proof.z(), &l);
+    let q = int(&exp / &l);
+    assert!(*proof.q() == Rsa2048::exp(&Rsa2048::op(&base, &Rsa2048::exp(&g, &alpha)), &q));
+    assert!(*proof.r() == int(&exp % &l));
+  }
+
+  #[test]
+  fn test_poke2_security_levels() {
+    let base = Rsa2048::elem(2);
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    for &(level, bits) in &[(SecurityLevel::Bits128, 128), (SecurityLevel::Bits256, 256)] {
+      let proof = Poke2::<Rsa2048>::prove_at_level(&base, &exp, &result, level);
+      assert!(Poke2::verify_at_level(&base, &result, &proof, level));
+      let l = Poke2::<Rsa2048>::challenge_at_level(&base, &result, proof.z(), level);
+      assert_eq!(l.significant_bits(), bits);
+      assert!(*proof.r() < l);
+      assert!(Poke2::from_bytes(&proof.to_bytes()) == Some(proof));
+    }
+    let proof = Poke2::<Rsa2048>::prove_at_level(&base, &exp, &result, SecurityLevel::Bits128);
+    assert!(!Poke2::verify(&base, &result, &proof));
+  }
+
+  #[test]
+  fn test_poke2_verify_detailed() {
+    let base = Rsa2048::elem(2);
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    assert_eq!(Poke2::verify_detailed(&base, &result, &proof), Ok(()));
+    let l = Poke2::<Rsa2048>::challenge(&base, &result, proof.z());
+
+    // With `r + l` and `Q / (base * g ^ alpha)` the equation still holds, so only the range check
+    // rejects the proof.
+    let alpha = Poke2::<Rsa2048>::alpha(&base, &result, proof.z(), &l);
+    let g = Rsa2048::unknown_order_elem();
+    let b = Rsa2048::op(&base, &Rsa2048::exp(&g, &alpha));
+    let mut unreduced = proof.clone();
+    unreduced.r += &l;
+    unreduced.Q = Rsa2048::op(&unreduced.Q, &Rsa2048::inv(&b));
+    assert_eq!(
+      Poke2::verify_detailed(&base, &result, &unreduced),
+      Err(Poke2Error::RemainderOutOfRange)
+    );
+    let mut negative = proof.clone();
+    negative.r = int(-1);
+    assert_eq!(
+      Poke2::verify_detailed(&base, &result, &negative),
+      Err(Poke2Error::RemainderOutOfRange)
+    );
+
+    let mut bad_q = proof.clone();
+    bad_q.Q = Rsa2048::op(&bad_q.Q, &base);
+    assert_eq!(
+      Poke2::verify_detailed(&base, &result, &bad_q),
+      Err(Poke2Error::EquationMismatch)
+    );
+    let mut bad_r = proof.clone();
+    bad_r.r = int(&bad_r.r ^ 1);
+    assert_eq!(
+      Poke2::verify_detailed(&base, &result, &bad_r),
+      Err(Poke2Error::EquationMismatch)
+    );
+    // Changing `z` changes `l`, so `r` may or may not be in range for the new challenge.
+    let mut bad_z = proof;
+    bad_z.z = Rsa2048::op(&bad_z.z, &base);
+    assert!(Poke2::verify_detailed(&base, &result, &bad_z).is_err());
+    assert!(!Poke2::verify(&base, &result, &bad_z));
+  }
+
+  #[test]
+  fn test_poke2_deterministic() {
+    let base = Rsa2048::elem(2);
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let proof_2 = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    assert_eq!(proof.to_bytes(), proof_2.to_bytes());
+    let level = SecurityLevel::Bits128;
+    assert_eq!(
+      Poke2::<Rsa2048>::prove_at_level(&base, &exp, &result, level).to_bytes(),
+      Poke2::<Rsa2048>::prove_at_level(&base, &exp, &result, level).to_bytes()
+    );
+  }
+
+  #[test]
+  fn test_poke2_verifier() {
+    let base = Rsa2048::elem(2);
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let mut bad_q = proof.clone();
+    bad_q.Q = Rsa2048::op(&bad_q.Q, &base);
+    let mut bad_r = proof.clone();
+    bad_r.r += 1;
+    let verifier = Poke2::verifier(&base, &result);
+    for candidate in &[proof, bad_q, bad_r] {
+      assert_eq!(
+        verifier.verify(candidate),
+        Poke2::verify(&base, &result, candidate)
+      );
+    }
+    assert!(verifier.verify(&Poke2::prove(&base, &exp, &result)));
+  }
+
+  /// The path benchmarked by `benches/proof/poke2_verify.rs`.
+  #[test]
+  fn test_poke2_verify_with_challenges() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let (l, alpha) = Poke2::<Rsa2048>::challenges(&base, &result, proof.z());
+    assert!(l == Poke2::<Rsa2048>::challenge(&base, &result, proof.z()));
+    assert!(Poke2::verify_with_challenges(&base, &result, &proof, &l, &alpha).is_ok());
+    assert!(Poke2::verify_with_challenges(&base, &result, &proof, &l, &int(&alpha + 1)).is_err());
+  }
+
+  // The multi-exponentiation check agrees with the original two-sided equation
+  // `Q ^ l * (base * g ^ alpha) ^ r = result * z ^ alpha` on honest and tampered proofs.
+  test_all_groups!(
+    test_poke2_multi_exp_check,
+    test_poke2_multi_exp_check_rsa2048,
+    test_poke2_multi_exp_check_class,
+  );
+  #[allow(non_snake_case)]
+  fn test_poke2_multi_exp_check<G: UnknownOrderGroup>() {
+    let original = |base: &G::Elem, result: &G::Elem, proof: &Poke2<G>, l, alpha| {
+      let Poke2 { z, Q, r } = proof;
+      let g = G::unknown_order_elem();
+      let lhs = G::op(&G::exp(Q, l), &G::exp(&G::op(base, &G::exp(&g, alpha)), r));
+      lhs == G::op(result, &G::exp(z, alpha))
+    };
+    let base = G::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = G::exp(&base, &exp);
+    let proof = Poke2::<G>::prove(&base, &exp, &result);
+    let (l, alpha) = Poke2::<G>::challenges(&base, &result, proof.z());
+    let mut bad_z = proof.clone();
+    bad_z.z = G::op(&bad_z.z, &base);
+    let mut bad_q = proof.clone();
+    bad_q.Q = G::op(&bad_q.Q, &base);
+    let mut bad_r = proof.clone();
+    bad_r.r += 1;
+    let wrong_result = G::op(&result, &base);
+    for &(result, proof) in &[
+      (&result, &proof),
+      (&result, &bad_z),
+      (&result, &bad_q),
+      (&result, &bad_r),
+      (&wrong_result, &proof),
+    ] {
+      assert_eq!(
+        Poke2::verify_with_challenges(&base, result, proof, &l, &alpha).is_ok(),
+        original(&base, result, proof, &l, &alpha)
+      );
+    }
+    assert!(original(&base, &result, &proof, &l, &alpha));
+  }
+
+  #[test]
+  fn test_poke2_bound_to_group() {
+    let transplant = |x: &DummyRsaElem| DummyRsa2::from_bytes(&DummyRsa::to_bytes(x)).unwrap();
+    let base = DummyRsa::elem(4);
+    let exp = int(1) << 300;
+    let result = DummyRsa::exp(&base, &exp);
+    let proof = Poke2::<DummyRsa>::prove(&base, &exp, &result);
+    let (base_2, result_2, z_2) = (
+      transplant(&base),
+      transplant(&result),
+      transplant(proof.z()),
+    );
+    assert!(
+      Poke2::<DummyRsa>::challenge(&base, &result, proof.z())
+        != Poke2::<DummyRsa2>::challenge(&base_2, &result_2, &z_2)
+    );
+    assert!(Poke2::verify(&base, &result, &proof));
+    let proof_2 = Poke2::<DummyRsa2>::from_bytes(&proof.to_bytes()).unwrap();
+    assert!(!Poke2::verify(&base_2, &result_2, &proof_2));
+  }
+
+  #[test]
+  fn test_poke2_zero_exp() {
+    let base = Rsa2048::elem(2);
+    let result = Rsa2048::id();
+    let proof = Poke2::<Rsa2048>::prove(&base, &int(0), &result);
+    assert!(Poke2::verify(&base, &result, &proof));
+    assert!(*proof.z() == Rsa2048::id());
+    assert!(*proof.q() == Rsa2048::id());
+    assert!(*proof.r() == 0);
+    assert!(!Poke2::verify(&base, &base, &proof));
+    assert!(Poke2::from_bytes(&proof.to_bytes()) == Some(proof));
+  }
+
   #[test]
   fn test_poke2_negative() {
     let base = Rsa2048::elem(2);
@@ -90,4 +617,93 @@ mod tests {
     let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
     assert!(Poke2::verify(&base, &result, &proof));
   }
+
+  #[test]
+  fn test_poke2_challenge() {
+    let base = Rsa2048::unknown_order_elem();
+    let exp = int(1) << 300;
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let l = Poke2::<Rsa2048>::challenge(&base, &result, &proof.z);
+    assert!(proof.r < l);
+    assert!(proof.r == int(&exp % &l));
+    assert!(l != Poke2::<Rsa2048>::challenge(&base, &result, &base));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_poke2_serde() {
+    let base = Rsa2048::elem(2);
+    let exp = int(-5);
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let json = serde_json::to_value(&proof).unwrap();
+    let keys = json.as_object().unwrap().keys().collect::<Vec<_>>();
+    assert_eq!(keys, ["q", "remainder", "version", "z"]);
+    assert_eq!(json["version"], 1);
+    assert!(serde_json::from_value::<Poke2<Rsa2048>>(json).unwrap() == proof);
+  }
+
+  /// `r` is encoded as fixed-width big-endian bytes, so equal remainders serialize identically
+  /// regardless of their magnitude or the proof they belong to.
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_poke2_serde_remainder() {
+    let exp = int(5);
+    let proofs = [Rsa2048::elem(2), Rsa2048::elem(3)]
+      .iter()
+      .map(|base| Poke2::<Rsa2048>::prove(base, &exp, &Rsa2048::exp(base, &exp)))
+      .collect::<Vec<_>>();
+    assert!(proofs[0].r == exp && proofs[1].r == exp);
+    let jsons = proofs
+      .iter()
+      .map(|proof| serde_json::to_value(proof).unwrap())
+      .collect::<Vec<_>>();
+    let remainder = jsons[0]["remainder"].as_str().unwrap();
+    assert_eq!(remainder.len(), 2 * R_BYTES);
+    assert_eq!(remainder, format!("{:0>64}", "05"));
+    assert_eq!(jsons[0]["remainder"], jsons[1]["remainder"]);
+    for (proof, json) in proofs.iter().zip(jsons) {
+      assert!(serde_json::from_value::<Poke2<Rsa2048>>(json).unwrap() == *proof);
+    }
+  }
+
+  #[test]
+  fn test_poke2_bytes() {
+    let base = Rsa2048::elem(2);
+    let exp = int(-5);
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let bytes = proof.to_bytes();
+    assert!(Poke2::from_bytes(&bytes) == Some(proof));
+    assert!(Poke2::<Rsa2048>::from_bytes(&bytes[1..]).is_none());
+  }
+
+  #[test]
+  fn test_poke2_try_from_bytes() {
+    let base = Rsa2048::elem(2);
+    let exp = int(-5);
+    let result = Rsa2048::exp(&base, &exp);
+    let proof = Poke2::<Rsa2048>::prove(&base, &exp, &result);
+    let mut bytes = proof.to_bytes();
+    assert!(Poke2::try_from(&bytes[..]) == Ok(proof));
+    for &len in &[0, 1, 256, 512, bytes.len() - 1] {
+      assert!(Poke2::<Rsa2048>::try_from(&bytes[..len]) == Err(ProofError::BadLength));
+    }
+    let elem_len = bytes.len() - R_BYTES;
+    for b in &mut bytes[..elem_len / 2] {
+      *b = 0;
+    }
+    assert!(Poke2::<Rsa2048>::try_from(&bytes[..]) == Err(ProofError::BadElem));
+    bytes.extend(vec![0; 1 << 16]);
+    assert!(Poke2::<Rsa2048>::try_from(&bytes[..]) == Err(ProofError::BadLength));
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  #[should_panic(expected = "result != base ^ exp")]
+  fn test_poke2_wrong_result() {
+    let base = Rsa2048::unknown_order_elem();
+    Poke2::<Rsa2048>::prove(&base, &int(20), &Rsa2048::elem(1_048_577));
+  }
 }
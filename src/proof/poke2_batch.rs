@@ -0,0 +1,107 @@
+//! Aggregated NI-PoKE2 for several exponents of a shared base. The statements are combined into one
+//! by a random linear combination derived from all of them, which is then proven with a single
+//! PoKE2.
+use super::{Poke2, Transcript};
+use crate::group::UnknownOrderGroup;
+use rug::Integer;
+
+/// Domain label of the Fiat-Shamir transcript for the combination coefficients.
+const DOMAIN: &[u8] = b"accumulator/poke2-batch/v1";
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+/// Struct for an aggregated NI-PoKE2 over statements `base ^ exp_i = result_i`.
+///
+/// The proof has the size of a single `Poke2`. It shows knowledge of `sum(gamma_i * exp_i)` for
+/// coefficients `gamma_i` derived from `base` and all results, so a batch in which some
+/// `base ^ exp_i != result_i` only verifies with negligible probability.
+pub struct Poke2Batch<G: UnknownOrderGroup>(Poke2<G>);
+
+impl<G: UnknownOrderGroup> Poke2Batch<G> {
+  /// Returns the combination coefficients `gamma_i`, one per result.
+  fn coefficients(base: &G::Elem, results: &[G::Elem]) -> Vec<Integer> {
+    let mut transcript = Transcript::new(DOMAIN);
+    transcript.append_descriptor::<G>();
+    transcript.append_elem::<G>(base);
+    for result in results {
+      transcript.append_elem::<G>(result);
+    }
+    (0..results.len())
+      .map(|i| {
+        let mut transcript = transcript.clone();
+        transcript.append_bigint(&Integer::from(i));
+        transcript.challenge_scalar()
+      })
+      .collect()
+  }
+
+  /// Returns the product of `result_i ^ gamma_i`.
+  fn combined_result(results: &[G::Elem], coefficients: &[Integer]) -> G::Elem {
+    results
+      .iter()
+      .zip(coefficients)
+      .fold(G::id(), |acc, (result, gamma)| {
+        G::op(&acc, &G::exp(result, gamma))
+      })
+  }
+
+  /// Computes a proof that you know `exps` s.t. `base ^ exps[i] = results[i]` for every `i`.
+  ///
+  /// Panics if `exps` and `results` have different lengths.
+  pub fn prove(base: &G::Elem, exps: &[Integer], results: &[G::Elem]) -> Self {
+    assert_eq!(
+      exps.len(),
+      results.len(),
+      "exps and results must have the same length"
+    );
+    let coefficients = Self::coefficients(base, results);
+    let exp: Integer = exps
+      .iter()
+      .zip(&coefficients)
+      .map(|(exp, gamma)| Integer::from(exp * gamma))
+      .sum();
+    let result = Self::combined_result(results, &coefficients);
+    Poke2Batch(Poke2::prove(base, &exp, &result))
+  }
+
+  /// Verifies that the prover knows exponents `exp_i` s.t. `base ^ exp_i = results[i]` for every
+  /// `i`.
+  pub fn verify(base: &G::Elem, results: &[G::Elem], Poke2Batch(proof): &Self) -> bool {
+    let coefficients = Self::coefficients(base, results);
+    let result = Self::combined_result(results, &coefficients);
+    Poke2::verify(base, &result, proof)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::{ElemFrom, Group, Rsa2048};
+  use crate::util::int;
+
+  #[test]
+  fn test_poke2_batch() {
+    let base = Rsa2048::elem(2);
+    let exps = [int(1) << 300, int(-5), int(12345)];
+    let results = exps
+      .iter()
+      .map(|exp| Rsa2048::exp(&base, exp))
+      .collect::<Vec<_>>();
+    let proof = Poke2Batch::<Rsa2048>::prove(&base, &exps, &results);
+    assert!(Poke2Batch::verify(&base, &results, &proof));
+
+    let mut wrong_results = results.clone();
+    wrong_results[1] = Rsa2048::exp(&base, &int(-4));
+    assert!(!Poke2Batch::verify(&base, &wrong_results, &proof));
+    assert!(!Poke2Batch::verify(&base, &results[..2], &proof));
+    let mut swapped_results = results.clone();
+    swapped_results.swap(0, 2);
+    assert!(!Poke2Batch::verify(&base, &swapped_results, &proof));
+  }
+
+  #[test]
+  #[should_panic(expected = "same length")]
+  fn test_poke2_batch_length_mismatch() {
+    let base = Rsa2048::elem(2);
+    Poke2Batch::<Rsa2048>::prove(&base, &[int(1), int(2)], &[Rsa2048::elem(4)]);
+  }
+}
@@ -0,0 +1,22 @@
+//! Macros shared by the unit tests of several modules.
+
+/// Instantiates the generic test function `$test_func` once per unknown-order group.
+macro_rules! test_all_groups {
+  ($test_func:ident, $func_name_rsa:ident, $func_name_class:ident, $($attr:meta)*) => {
+    #[test]
+    $(
+      #[$attr]
+    )*
+    fn $func_name_rsa() {
+      $test_func::<$crate::group::Rsa2048>();
+    }
+
+    #[test]
+    $(
+      #[$attr]
+    )*
+    fn $func_name_class() {
+      $test_func::<$crate::group::ClassGroup>();
+    }
+  };
+}
@@ -0,0 +1,677 @@
+//! Stateful accumulator that tracks the product of its accumulated primes, so that witnesses and
+//! membership proofs can be derived without an external witness store.
+use crate::accumulator::{AccError, Accumulator, MembershipProof, NonmembershipProof, Witness};
+use crate::group::{GroupDescriptor, GroupError, UnknownOrderGroup};
+use crate::hash::{hash, hash_to_prime, Blake2b};
+use crate::util::{int, to_fixed_be_bytes};
+use rug::integer::Order;
+use rug::Integer;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::hash::Hash;
+
+/// Leading byte of the encoding produced by `TrackedAccumulator::serialize`. Bumped whenever that
+/// encoding changes.
+const SERIALIZATION_VERSION: u8 = 2;
+
+/// Byte length of each prime in the encoding produced by `TrackedAccumulator::serialize`. Primes
+/// from `hash_to_prime` have at most 256 bits.
+const PRIME_BYTES: usize = 32;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A Bloom filter over hashable items, used by `TrackedAccumulator::with_bloom_filter` to answer
+/// "definitely not present" without hashing to a prime. Items cannot be removed, so the filter
+/// only ever has false positives, never false negatives.
+pub struct BloomFilter {
+  bits: Vec<u64>,
+  n_bits: u64,
+  n_hashes: u32,
+}
+
+impl BloomFilter {
+  /// Returns an empty filter of `n_bits` bits (rounded up to a multiple of 64) that sets
+  /// `n_hashes` bits per item. For `n` items, `n_hashes = (n_bits / n) * ln 2` minimizes the
+  /// false-positive rate.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `n_bits` or `n_hashes` is zero.
+  pub fn new(n_bits: usize, n_hashes: u32) -> Self {
+    assert!(n_bits > 0 && n_hashes > 0, "empty Bloom filter");
+    let n_words = n_bits.div_ceil(64);
+    Self {
+      bits: vec![0; n_words],
+      n_bits: 64 * n_words as u64,
+      n_hashes,
+    }
+  }
+
+  /// Records `t` in the filter.
+  pub fn insert<T: Hash + ?Sized>(&mut self, t: &T) {
+    for i in self.indices(t) {
+      self.bits[(i / 64) as usize] |= 1 << (i % 64);
+    }
+  }
+
+  /// Returns `false` if `t` was definitely never inserted, and `true` if it may have been.
+  pub fn might_contain<T: Hash + ?Sized>(&self, t: &T) -> bool {
+    self
+      .indices(t)
+      .all(|i| self.bits[(i / 64) as usize] & (1 << (i % 64)) != 0)
+  }
+
+  // Derives the bit indices of `t` from one Blake2b digest by double hashing.
+  fn indices<T: Hash + ?Sized>(&self, t: &T) -> impl Iterator<Item = u64> {
+    let digest = hash(&Blake2b::default, t);
+    let h1 = u64::from_le_bytes(*array_ref![digest, 0, 8]);
+    let h2 = u64::from_le_bytes(*array_ref![digest, 8, 8]) | 1;
+    let n_bits = self.n_bits;
+    (0..u64::from(self.n_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % n_bits)
+  }
+}
+
+#[derive(Debug, Eq, Hash, PartialEq)]
+/// An accumulator together with the primes it has accumulated and their running product. Suitable
+/// for servers that can afford to store them (which grows linearly in the number of elements) in
+/// exchange for computing any witness on demand.
+///
+/// Optionally keeps a bounded history of prior states so that recent updates can be undone, e.g.
+/// on a block reorg.
+pub struct TrackedAccumulator<G: UnknownOrderGroup, T> {
+  acc: Accumulator<G, T>,
+  product: Integer,
+  primes: Vec<Integer>,
+  history: VecDeque<(Accumulator<G, T>, Integer, Vec<Integer>)>,
+  max_history: usize,
+  bloom: Option<BloomFilter>,
+  max_exponent_bits: Option<u32>,
+}
+
+// Manual clone impl required for the same reason as `Accumulator`.
+impl<G: UnknownOrderGroup, T: Hash> Clone for TrackedAccumulator<G, T> {
+  fn clone(&self) -> Self {
+    Self {
+      acc: self.acc.clone(),
+      product: self.product.clone(),
+      primes: self.primes.clone(),
+      history: self.history.clone(),
+      max_history: self.max_history,
+      bloom: self.bloom.clone(),
+      max_exponent_bits: self.max_exponent_bits,
+    }
+  }
+}
+
+impl<G: UnknownOrderGroup, T: Eq + Hash> TrackedAccumulator<G, T> {
+  /// Returns a new, empty tracked accumulator that keeps no history.
+  pub fn empty() -> Self {
+    Self::with_history(0)
+  }
+
+  /// Returns a new, empty tracked accumulator that remembers the states before its last
+  /// `max_history` updates. Older states are discarded, after which they cannot be restored by
+  /// `undo`.
+  pub fn with_history(max_history: usize) -> Self {
+    Self {
+      acc: Accumulator::empty(),
+      product: int(1),
+      primes: Vec::new(),
+      history: VecDeque::with_capacity(max_history),
+      max_history,
+      bloom: None,
+      max_exponent_bits: None,
+    }
+  }
+
+  /// Caps the bit length of the prime hash product of the elements passed to a single `try_add` or
+  /// `delete` at `max_bits`, so that servers processing untrusted input cannot be forced into
+  /// arbitrarily long exponentiations. Each element contributes about 256 bits. There is no cap by
+  /// default, and the cap is not included in `serialize`.
+  pub fn with_max_exponent_bits(mut self, max_bits: u32) -> Self {
+    self.max_exponent_bits = Some(max_bits);
+    self
+  }
+
+  /// Maintains a `BloomFilter::new(n_bits, n_hashes)` of added elements alongside the accumulator,
+  /// so that `contains` can reject most absent elements without hashing them to primes.
+  ///
+  /// Deleting or undoing does not clear bits, so deleted elements become false positives, which
+  /// `contains` still answers correctly at full cost. The filter is not included in `serialize`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the accumulator is not empty, since earlier elements would be false negatives.
+  pub fn with_bloom_filter(mut self, n_bits: usize, n_hashes: u32) -> Self {
+    assert!(
+      self.product == 1,
+      "Bloom filter added to a non-empty accumulator"
+    );
+    self.bloom = Some(BloomFilter::new(n_bits, n_hashes));
+    self
+  }
+
+  /// Returns the Bloom filter of added elements, if enabled by `with_bloom_filter`.
+  pub fn bloom_filter(&self) -> Option<&BloomFilter> {
+    self.bloom.as_ref()
+  }
+
+  /// Returns whether `elem` is accumulated, by checking that its prime divides the running product.
+  /// If a Bloom filter is enabled and rules `elem` out, returns `false` without computing the
+  /// prime.
+  pub fn contains(&self, elem: &T) -> bool {
+    if let Some(bloom) = &self.bloom {
+      if !bloom.might_contain(elem) {
+        return false;
+      }
+    }
+    self.product.is_divisible(&hash_to_prime(elem))
+  }
+
+  /// Returns the underlying accumulator.
+  pub fn accumulator(&self) -> &Accumulator<G, T> {
+    &self.acc
+  }
+
+  /// Returns the product of the primes currently accumulated.
+  pub fn product(&self) -> &Integer {
+    &self.product
+  }
+
+  /// Adds `elems` to the accumulator. As with `Accumulator::add`, it is up to clients to ensure
+  /// uniqueness.
+  ///
+  /// Panics if the cap set by `with_max_exponent_bits` is exceeded. Use `try_add` for untrusted
+  /// input.
+  pub fn add(&mut self, elems: &[T]) {
+    self
+      .try_add(elems)
+      .expect("exponent exceeds the configured cap");
+  }
+
+  /// Like `add`, but returns `ExponentTooLarge` (leaving the accumulator unchanged) if the prime
+  /// hash product of `elems` exceeds the cap set by `with_max_exponent_bits`.
+  pub fn try_add(&mut self, elems: &[T]) -> Result<(), AccError> {
+    let primes = elems.iter().map(hash_to_prime).collect::<Vec<_>>();
+    let x = primes.iter().product();
+    self.check_exponent(&x)?;
+    self.record_history();
+    self.acc = Accumulator::new_from(G::exp(&self.acc.value, &x));
+    self.product *= x;
+    self.primes.extend(primes);
+    if let Some(bloom) = &mut self.bloom {
+      for elem in elems {
+        bloom.insert(elem);
+      }
+    }
+    Ok(())
+  }
+
+  fn check_exponent(&self, x: &Integer) -> Result<(), AccError> {
+    match self.max_exponent_bits {
+      Some(max_bits) if x.significant_bits() > max_bits => Err(AccError::ExponentTooLarge),
+      _ => Ok(()),
+    }
+  }
+
+  /// Removes `elems` from the accumulator, recomputing it from the running product so that no
+  /// witnesses are needed. Returns `NotPresent` (leaving the accumulator unchanged) if some element
+  /// is not accumulated, counting multiplicity, before doing any group operations, and
+  /// `ExponentTooLarge` if the prime hash product of `elems` exceeds the cap set by
+  /// `with_max_exponent_bits`.
+  pub fn delete(&mut self, elems: &[T]) -> Result<(), AccError> {
+    let mut product = self.product.clone();
+    let mut primes = self.primes.clone();
+    let mut deleted = int(1);
+    for elem in elems {
+      let p = hash_to_prime(elem);
+      deleted *= &p;
+      self.check_exponent(&deleted)?;
+      let i = primes
+        .iter()
+        .position(|q| *q == p)
+        .ok_or(AccError::NotPresent)?;
+      primes.swap_remove(i);
+      product.div_exact_mut(&p);
+    }
+    self.record_history();
+    self.acc = Accumulator::new_from(G::exp(&G::unknown_order_elem(), &product));
+    self.product = product;
+    self.primes = primes;
+    Ok(())
+  }
+
+  /// Restores the state before the most recent `add` or `delete`. Returns `HistoryExhausted` if
+  /// there is no recorded state, i.e. if more updates were undone than the history holds.
+  pub fn undo(&mut self) -> Result<(), AccError> {
+    let (acc, product, primes) = self.history.pop_back().ok_or(AccError::HistoryExhausted)?;
+    self.acc = acc;
+    self.product = product;
+    self.primes = primes;
+    Ok(())
+  }
+
+  /// Returns a self-describing encoding of the accumulator: a version byte followed by the group
+  /// descriptor (family and parameters), `G::to_bytes` of the accumulator value, the accumulated
+  /// primes as consecutive 32-byte big-endian integers and the history bound, each preceded by its
+  /// length as a 4-byte big-endian integer.
+  ///
+  /// The undo history itself is not included.
+  pub fn serialize(&self) -> Vec<u8> {
+    let GroupDescriptor { family, params } = G::descriptor();
+    let primes_bytes = self
+      .primes
+      .iter()
+      .flat_map(|p| to_fixed_be_bytes(p, PRIME_BYTES))
+      .collect::<Vec<_>>();
+    let mut bytes = vec![SERIALIZATION_VERSION];
+    for component in &[
+      family.as_bytes(),
+      &params,
+      &G::to_bytes(&self.acc.value),
+      &primes_bytes,
+      &(self.max_history as u64).to_be_bytes(),
+    ] {
+      let len = u32::try_from(component.len()).expect("component too long");
+      bytes.extend_from_slice(&len.to_be_bytes());
+      bytes.extend_from_slice(component);
+    }
+    bytes
+  }
+
+  /// Decodes an accumulator from the encoding produced by `serialize`, with an empty undo history.
+  /// Returns `DescriptorMismatch` if it was serialized over a group other than `G`, and `Malformed`
+  /// if `bytes` is not a valid encoding.
+  ///
+  /// The primes are trusted to match the accumulator value, since checking them costs as much as
+  /// recomputing the accumulator.
+  pub fn deserialize(bytes: &[u8]) -> Result<Self, GroupError> {
+    let (&version, mut rest) = bytes.split_first().ok_or(GroupError::Malformed)?;
+    if version != SERIALIZATION_VERSION {
+      return Err(GroupError::Malformed);
+    }
+    let mut components = Vec::with_capacity(5);
+    while !rest.is_empty() && components.len() < 5 {
+      if rest.len() < 4 {
+        return Err(GroupError::Malformed);
+      }
+      let (len, tail) = rest.split_at(4);
+      let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+      if tail.len() < len {
+        return Err(GroupError::Malformed);
+      }
+      components.push(&tail[..len]);
+      rest = &tail[len..];
+    }
+    if components.len() != 5 || !rest.is_empty() {
+      return Err(GroupError::Malformed);
+    }
+
+    let descriptor = G::descriptor();
+    if components[0] != descriptor.family.as_bytes() || components[1] != &descriptor.params[..] {
+      return Err(GroupError::DescriptorMismatch);
+    }
+    let value = G::from_bytes(components[2]).ok_or(GroupError::Malformed)?;
+    if components[3].len() % PRIME_BYTES != 0 {
+      return Err(GroupError::Malformed);
+    }
+    let primes = components[3]
+      .chunks(PRIME_BYTES)
+      .map(|chunk| Integer::from_digits(chunk, Order::Msf))
+      .collect::<Vec<_>>();
+    let product: Integer = primes.iter().product();
+    if product == 0 {
+      return Err(GroupError::Malformed);
+    }
+    let max_history = match components[4] {
+      &[b0, b1, b2, b3, b4, b5, b6, b7] => {
+        usize::try_from(u64::from_be_bytes([b0, b1, b2, b3, b4, b5, b6, b7]))
+          .map_err(|_| GroupError::Malformed)?
+      }
+      _ => return Err(GroupError::Malformed),
+    };
+    Ok(Self {
+      acc: Accumulator::new_from(value),
+      product,
+      primes,
+      history: VecDeque::new(),
+      max_history,
+      bloom: None,
+      max_exponent_bits: None,
+    })
+  }
+
+  fn record_history(&mut self) {
+    if self.max_history == 0 {
+      return;
+    }
+    if self.history.len() == self.max_history {
+      self.history.pop_front();
+    }
+    self
+      .history
+      .push_back((self.acc.clone(), self.product.clone(), self.primes.clone()));
+  }
+
+  /// Returns the primes currently accumulated, in the order they were added (up to reordering by
+  /// `delete`).
+  pub fn primes(&self) -> &[Integer] {
+    &self.primes
+  }
+
+  /// Iterates over the primes currently accumulated, in the order of `primes`. Only a
+  /// `TrackedAccumulator` can do this: a plain `Accumulator` keeps no record of its elements.
+  pub fn iter(&self) -> std::slice::Iter<'_, Integer> {
+    self.primes.iter()
+  }
+
+  /// Returns every accumulated prime together with its membership witness
+  /// `g ^ (product / prime)`, computed at once via `Witness::compute_individual_witnesses` in
+  /// O(N log N) group operations, e.g. for a server handing each client its witness.
+  pub fn all_witnesses(&self) -> Vec<(Integer, G::Elem)> {
+    if self.primes.is_empty() {
+      return Vec::new();
+    }
+    // The element type is irrelevant to the witnesses, which only depend on the primes.
+    let witnesses = Witness(Accumulator::<G, ()>::empty()).root_factor(&self.primes);
+    self
+      .primes
+      .iter()
+      .cloned()
+      .zip(witnesses.into_iter().map(|w| w.0.value))
+      .collect()
+  }
+
+  /// Computes a membership proof for `elem` from the running product alone, using the witness
+  /// `g ^ (product / elem)`. Returns `BadWitness` if `elem` is not accumulated.
+  pub fn prove_membership(&self, elem: &T) -> Result<MembershipProof<G, T>, AccError> {
+    let (_, proof) = Accumulator::delete_from_product(
+      &G::unknown_order_elem(),
+      &self.product,
+      std::slice::from_ref(elem),
+    )?;
+    Ok(proof)
+  }
+
+  /// Computes a non-membership proof for `elems` from the running product, via
+  /// `Accumulator::prove_nonmembership_with_product`. Unlike `Accumulator::prove_nonmembership`,
+  /// the set is never rehashed, so repeated queries against a stable set only pay for `elems`, and
+  /// the product stays in sync across `add`, `delete` and `undo`.
+  ///
+  /// Returns `InputsNotCoprime` if some element of `elems` is accumulated, and `EmptyElems` if
+  /// `elems` is empty.
+  pub fn prove_nonmembership(&self, elems: &[T]) -> Result<NonmembershipProof<G, T>, AccError> {
+    self
+      .acc
+      .prove_nonmembership_with_product(&self.product, elems)
+  }
+}
+
+impl<'a, G: UnknownOrderGroup, T: Eq + Hash> IntoIterator for &'a TrackedAccumulator<G, T> {
+  type Item = &'a Integer;
+  type IntoIter = std::slice::Iter<'a, Integer>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::group::{ClassGroup, Rsa2048};
+  use crate::util::prime_hash_product;
+  use std::cell::RefCell;
+  use std::hash::Hasher;
+
+  thread_local! {
+    static HASHED: RefCell<Vec<u32>> = RefCell::default();
+  }
+
+  /// An element that records its value each time it is hashed, e.g. by `hash_to_prime`.
+  #[derive(Clone, Debug, PartialEq, Eq)]
+  struct Recorded(u32);
+
+  impl Hash for Recorded {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+      HASHED.with(|hashed| hashed.borrow_mut().push(self.0));
+      self.0.hash(state);
+    }
+  }
+
+  fn take_hashed() -> Vec<u32> {
+    HASHED.with(|hashed| hashed.replace(Vec::new()))
+  }
+
+  test_all_groups!(
+    test_prove_membership,
+    test_prove_membership_rsa2048,
+    test_prove_membership_class,
+  );
+  fn test_prove_membership<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d"];
+    let mut tracked = TrackedAccumulator::<G, &'static str>::empty();
+    tracked.add(&elems[..2]);
+    tracked.add(&elems[2..]);
+    assert!(*tracked.accumulator() == Accumulator::empty().add(&elems));
+    for elem in &elems {
+      let proof = tracked.prove_membership(elem).unwrap();
+      assert!(tracked.accumulator().verify_membership(elem, &proof));
+    }
+    assert!(tracked.prove_membership(&"e").is_err());
+  }
+
+  test_all_groups!(
+    test_prove_nonmembership,
+    test_prove_nonmembership_rsa2048,
+    test_prove_nonmembership_class,
+  );
+  fn test_prove_nonmembership<G: UnknownOrderGroup>() {
+    let mut tracked = TrackedAccumulator::<G, &'static str>::with_history(1);
+    tracked.add(&["a", "b", "c"]);
+    let product = tracked.product().clone();
+    for elems in &[&["d"][..], &["e", "f"][..]] {
+      let proof = tracked.prove_nonmembership(elems).unwrap();
+      assert!(tracked.accumulator().verify_nonmembership(elems, &proof));
+    }
+    assert!(*tracked.product() == product);
+    assert!(tracked.prove_nonmembership(&["a"]).is_err());
+
+    // Proofs track updates to the set.
+    tracked.delete(&["a"]).unwrap();
+    let proof = tracked.prove_nonmembership(&["a"]).unwrap();
+    assert!(tracked.accumulator().verify_nonmembership(&["a"], &proof));
+    tracked.undo().unwrap();
+    assert!(tracked.prove_nonmembership(&["a"]).is_err());
+  }
+
+  #[test]
+  fn test_prove_nonmembership_no_rehash() {
+    let set = (0..8).map(Recorded).collect::<Vec<_>>();
+    let mut tracked = TrackedAccumulator::<Rsa2048, Recorded>::empty();
+    tracked.add(&set);
+    take_hashed();
+    for elems in &[vec![Recorded(8)], vec![Recorded(9), Recorded(10)]] {
+      let proof = tracked.prove_nonmembership(elems).unwrap();
+      assert!(tracked.accumulator().verify_nonmembership(elems, &proof));
+      let hashed = take_hashed();
+      assert!(!hashed.is_empty());
+      assert!(hashed.iter().all(|x| *x >= 8), "set element rehashed");
+    }
+    // Proving from the set instead rehashes all of it.
+    tracked
+      .accumulator()
+      .prove_nonmembership(&set, &[Recorded(8)])
+      .unwrap();
+    let hashed = take_hashed();
+    assert!((0..8).all(|x| hashed.contains(&x)));
+  }
+
+  #[test]
+  fn test_iter() {
+    let mut tracked = TrackedAccumulator::<Rsa2048, u64>::empty();
+    tracked.add(&[5, 7, 11]);
+    tracked.delete(&[7]).unwrap();
+    let mut primes = tracked.iter().cloned().collect::<Vec<_>>();
+    primes.sort();
+    let mut expected = vec![hash_to_prime(&5_u64), hash_to_prime(&11_u64)];
+    expected.sort();
+    assert_eq!(primes, expected);
+    assert!((&tracked).into_iter().eq(tracked.primes()));
+  }
+
+  test_all_groups!(test_delete, test_delete_rsa2048, test_delete_class,);
+  fn test_delete<G: UnknownOrderGroup>() {
+    let mut tracked = TrackedAccumulator::<G, &'static str>::empty();
+    tracked.add(&["a", "b", "c"]);
+    tracked.delete(&["a", "c"]).unwrap();
+    assert!(*tracked.accumulator() == Accumulator::empty().add(&["b"]));
+    assert!(*tracked.product() == prime_hash_product(&["b"]));
+    assert!(tracked.delete(&["a"]).is_err());
+    assert!(*tracked.product() == prime_hash_product(&["b"]));
+  }
+
+  test_all_groups!(
+    test_delete_not_present,
+    test_delete_not_present_rsa2048,
+    test_delete_not_present_class,
+  );
+  fn test_delete_not_present<G: UnknownOrderGroup>() {
+    let mut tracked = TrackedAccumulator::<G, &'static str>::empty();
+    tracked.add(&["a", "b"]);
+    let before = tracked.clone();
+    match tracked.delete(&["c"]) {
+      Err(AccError::NotPresent) => {}
+      _ => panic!("expected NotPresent"),
+    }
+    // Deleting an element more times than it was added is caught too.
+    match tracked.delete(&["a", "a"]) {
+      Err(AccError::NotPresent) => {}
+      _ => panic!("expected NotPresent"),
+    }
+    assert!(tracked.accumulator() == before.accumulator());
+    assert!(tracked.product() == before.product());
+
+    tracked.delete(&["a"]).unwrap();
+    assert!(*tracked.accumulator() == Accumulator::empty().add(&["b"]));
+  }
+
+  test_all_groups!(test_undo, test_undo_rsa2048, test_undo_class,);
+  fn test_undo<G: UnknownOrderGroup>() {
+    let mut tracked = TrackedAccumulator::<G, &'static str>::with_history(2);
+    tracked.add(&["a"]);
+    let after_one_batch = tracked.clone();
+    tracked.add(&["b", "c"]);
+    tracked.add(&["d"]);
+    tracked.undo().unwrap();
+    tracked.undo().unwrap();
+    assert!(tracked.accumulator() == after_one_batch.accumulator());
+    assert!(tracked.product() == after_one_batch.product());
+    // The state before the first batch fell out of the history.
+    assert!(tracked.undo().is_err());
+  }
+
+  #[test]
+  fn test_bloom_filter() {
+    let mut tracked = TrackedAccumulator::<Rsa2048, String>::empty().with_bloom_filter(4096, 4);
+    let added = (0..100).map(|i| format!("added {}", i)).collect::<Vec<_>>();
+    tracked.add(&added);
+    let bloom = tracked.bloom_filter().unwrap();
+    for elem in &added {
+      assert!(bloom.might_contain(elem));
+      assert!(tracked.contains(elem));
+    }
+    let absent = (0..100)
+      .map(|i| format!("absent {}", i))
+      .collect::<Vec<_>>();
+    let fast_negatives = absent.iter().filter(|e| !bloom.might_contain(*e)).count();
+    assert!(fast_negatives >= 90);
+    assert!(absent.iter().all(|e| !tracked.contains(e)));
+
+    // Deleted elements remain in the filter, but `contains` falls back to the product.
+    tracked.delete(&added[..1]).unwrap();
+    assert!(tracked.bloom_filter().unwrap().might_contain(&added[0]));
+    assert!(!tracked.contains(&added[0]));
+  }
+
+  #[test]
+  fn test_max_exponent_bits() {
+    let elems = ["a", "b", "c", "d"];
+    // Four 256-bit primes have a product of at most 1024 bits.
+    let mut tracked =
+      TrackedAccumulator::<Rsa2048, &'static str>::empty().with_max_exponent_bits(1024);
+    tracked.try_add(&elems).unwrap();
+    match tracked.try_add(&["e", "f", "g", "h", "i"]) {
+      Err(AccError::ExponentTooLarge) => {}
+      _ => panic!("expected ExponentTooLarge"),
+    }
+    assert!(*tracked.product() == prime_hash_product(&elems));
+    let mut too_many = tracked.clone().with_max_exponent_bits(300);
+    match too_many.delete(&elems[..2]) {
+      Err(AccError::ExponentTooLarge) => {}
+      _ => panic!("expected ExponentTooLarge"),
+    }
+    assert!(too_many.product() == tracked.product());
+    tracked.delete(&elems[..2]).unwrap();
+    assert!(*tracked.accumulator() == Accumulator::empty().add(&elems[2..]));
+  }
+
+  test_all_groups!(
+    test_all_witnesses,
+    test_all_witnesses_rsa2048,
+    test_all_witnesses_class,
+  );
+  fn test_all_witnesses<G: UnknownOrderGroup>() {
+    let elems = ["a", "b", "c", "d", "e"];
+    let mut tracked = TrackedAccumulator::<G, &'static str>::empty();
+    assert!(tracked.all_witnesses().is_empty());
+    tracked.add(&elems[..2]);
+    tracked.add(&elems[2..]);
+    let witnesses = tracked.all_witnesses();
+    assert_eq!(witnesses.len(), elems.len());
+    for (elem, (prime, witness)) in elems.iter().zip(&witnesses) {
+      assert!(*prime == hash_to_prime(elem));
+      assert!(G::exp(witness, prime) == tracked.accumulator().value);
+    }
+    tracked.delete(&["b"]).unwrap();
+    let witnesses = tracked.all_witnesses();
+    assert_eq!(witnesses.len(), elems.len() - 1);
+    assert!(witnesses
+      .iter()
+      .all(|(prime, witness)| G::exp(witness, prime) == tracked.accumulator().value));
+  }
+
+  #[test]
+  fn test_serialize() {
+    let mut tracked = TrackedAccumulator::<Rsa2048, &'static str>::with_history(3);
+    tracked.add(&["a", "b", "c"]);
+    let bytes = tracked.serialize();
+    let restored = TrackedAccumulator::<Rsa2048, &'static str>::deserialize(&bytes).unwrap();
+    assert!(restored.accumulator() == tracked.accumulator());
+    assert!(restored.product() == tracked.product());
+    assert!(restored.primes() == tracked.primes());
+    for elem in &["a", "b", "c"] {
+      assert!(restored.prove_membership(elem).unwrap() == tracked.prove_membership(elem).unwrap());
+    }
+    assert!(restored.clone().undo().is_err());
+    let mut restored = restored;
+    restored.add(&["d"]);
+    assert!(restored.undo().is_ok());
+
+    assert!(
+      TrackedAccumulator::<ClassGroup, &'static str>::deserialize(&bytes)
+        == Err(GroupError::DescriptorMismatch)
+    );
+    for len in &[0, 1, 5, bytes.len() - 1] {
+      assert!(
+        TrackedAccumulator::<Rsa2048, &'static str>::deserialize(&bytes[..*len])
+          == Err(GroupError::Malformed)
+      );
+    }
+    let mut extended = bytes.clone();
+    extended.push(0);
+    assert!(
+      TrackedAccumulator::<Rsa2048, &'static str>::deserialize(&extended)
+        == Err(GroupError::Malformed)
+    );
+  }
+}
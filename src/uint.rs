@@ -189,16 +189,7 @@ macro_rules! u_types {
 
       impl PartialOrd for $t {
         fn partial_cmp(&self, x: &Self) -> Option<Ordering> {
-          let x = unsafe { gmp::mpn_cmp(self.data(), x.data(), $size) };
-          Some({
-            if x < 0 {
-              Ordering::Less
-            } else if x == 0 {
-              Ordering::Equal
-            } else {
-              Ordering::Greater
-            }
-          })
+          Some(self.cmp(x))
         }
       }
 
@@ -530,10 +521,10 @@ where
 }
 
 fn i32_to_mpz(i: i32, data: &mut u64) -> mpz_t {
-  *data = i.abs() as u64;
+  *data = i.unsigned_abs() as u64;
   mpz_t {
     size: i.signum(),
-    d: mut_ptr(&data),
+    d: mut_ptr(data),
     alloc: 1,
   }
 }
@@ -553,7 +544,7 @@ mod tests {
   #[should_panic(expected = "assertion failed: carry == 0")]
   #[test]
   fn test_add_overflow() {
-    let _ = u256([0, 0, 0, u64::max_value()]) + u256([0, 0, 0, u64::max_value()]);
+    let _ = u256([0, 0, 0, u64::MAX]) + u256([0, 0, 0, u64::MAX]);
   }
 
   #[test]
@@ -1,6 +1,12 @@
 //! Miscellaneous functions used throughout the library.
 use crate::group::Group;
 use crate::hash::hash_to_prime;
+#[cfg(test)]
+use rand_chacha::ChaChaRng;
+use rand_core::RngCore;
+#[cfg(test)]
+use rand_core::SeedableRng;
+use rug::integer::Order;
 use rug::Integer;
 use std::hash::Hash;
 
@@ -22,11 +28,104 @@ where
   Integer::from(val)
 }
 
+/// Returns the magnitude of `x` as a big-endian byte string of exactly `len` bytes.
+///
+/// Panics if `|x|` does not fit in `len` bytes.
+pub fn to_fixed_be_bytes(x: &Integer, len: usize) -> Vec<u8> {
+  let digits = x.to_digits::<u8>(Order::Msf);
+  assert!(digits.len() <= len, "integer does not fit in {} bytes", len);
+  let mut bytes = vec![0; len - digits.len()];
+  bytes.extend_from_slice(&digits);
+  bytes
+}
+
+/// Returns an integer sampled uniformly from `[0, 2 ^ bits)` using `rng`.
+///
+/// Randomized functions in this crate take their randomness from a caller-provided `RngCore`, so
+/// that tests can pass a seeded RNG (see `test_rng`) and get reproducible outputs.
+pub fn random_bits(rng: &mut impl RngCore, bits: u32) -> Integer {
+  let mut bytes = vec![0; (bits as usize).div_ceil(8)];
+  rng.fill_bytes(&mut bytes);
+  Integer::from_digits(&bytes, Order::Msf).keep_bits(bits)
+}
+
+/// Returns a deterministic RNG seeded with `seed`, for reproducible tests of randomized functions.
+#[cfg(test)]
+pub fn test_rng(seed: u64) -> ChaChaRng {
+  ChaChaRng::seed_from_u64(seed)
+}
+
+/// Computes `base ^ n mod modulus` for nonnegative `n` by plain left-to-right square-and-multiply,
+/// as a slow reference for testing group exponentiation.
+#[cfg(test)]
+pub fn square_and_multiply(base: &Integer, n: &Integer, modulus: &Integer) -> Integer {
+  assert!(*n >= 0);
+  let mut res = int(1);
+  for i in (0..n.significant_bits()).rev() {
+    res = res.square() % modulus;
+    if n.get_bit(i) {
+      res = res * base % modulus;
+    }
+  }
+  res
+}
+
 /// Hashes its arguments to primes and takes their product.
 pub fn prime_hash_product<T: Hash>(ts: &[T]) -> Integer {
   ts.iter().map(hash_to_prime).product()
 }
 
+/// Returns the sum of the bit lengths of `xs`, a cheap upper bound on the bit length of their
+/// product that can be used to budget memory or time before computing it.
+///
+/// The bound is tight up to `xs.len() - 1` bits: each factor beyond the first can lose at most one
+/// bit relative to the sum.
+pub fn product_bits(xs: &[Integer]) -> u64 {
+  xs.iter().map(|x| u64::from(x.significant_bits())).sum()
+}
+
+/// Returns the product of nonnegative `xs` mod `modulus`, reducing after each multiplication so
+/// that the full product is never built. Useful for computing a PoE remainder `r = x mod l` when
+/// `x` is a product of many primes.
+///
+/// Panics if `modulus` is zero.
+pub fn product_mod(xs: &[Integer], modulus: &Integer) -> Integer {
+  xs.iter()
+    .fold(int(1) % modulus, |acc, x| int(acc * x) % modulus)
+}
+
+/// Returns `base ^ (x_1 * ... * x_n)` for the exponents `x_1, ..., x_n` yielded by `xs`, by
+/// exponentiating by each in turn so that the product is never built. Trades the memory of a huge
+/// exponent for slower exponentiation, since `G::exp` is called once per element.
+pub fn exp_product<'a, G: Group>(
+  base: &G::Elem,
+  xs: impl IntoIterator<Item = &'a Integer>,
+) -> G::Elem {
+  xs.into_iter().fold(base.clone(), |acc, x| G::exp(&acc, x))
+}
+
+/// Returns `base ^ (x_1 * ... * x_n)` for the possibly negative exponents `x_1, ..., x_n` yielded
+/// by `xs`. Unlike `exp_product`, the product is built first, so that there is a single
+/// exponentiation and at most one inversion.
+pub fn exp_signed_product<'a, G: Group>(
+  base: &G::Elem,
+  xs: impl IntoIterator<Item = &'a Integer>,
+) -> G::Elem {
+  let x: Integer = xs.into_iter().product();
+  G::exp(base, &x)
+}
+
+/// Returns `(gcd, x, y)` with `gcd = gcd(a, b) >= 0` and `a * x + b * y = gcd`.
+///
+/// This is GMP's extended Euclidean algorithm, which is iterative, so deep inputs are fine. The
+/// coefficients are GMP's: when `|a| != |b|` and neither divides the other, `|x| < |b| / (2 * gcd)`
+/// and `|y| < |a| / (2 * gcd)`, so for positive `a` and `b` they have opposite signs. Callers such
+/// as `shamir_trick` and `Accumulator::prove_nonmembership` exponentiate by them directly and rely
+/// on `G::exp` to invert the base for the negative one.
+pub fn bezout(a: &Integer, b: &Integer) -> (Integer, Integer, Integer) {
+  <(Integer, Integer, Integer)>::from(a.gcd_cofactors_ref(b))
+}
+
 /// Computes the `(xy)`th root of `g` given the `x`th and `y`th roots of `g` and `(x, y)` coprime.
 // TODO: Consider moving this to the `accumulator` module?
 #[allow(clippy::similar_names)]
@@ -40,7 +139,7 @@ pub fn shamir_trick<G: Group>(
     return None;
   }
 
-  let (gcd, a, b) = <(Integer, Integer, Integer)>::from(x.gcd_cofactors_ref(&y));
+  let (gcd, a, b) = bezout(x, y);
 
   if gcd != int(1) {
     return None;
@@ -57,7 +156,7 @@ pub fn solve_linear_congruence(
   m: &Integer,
 ) -> Option<(Integer, Integer)> {
   // g = gcd(a, m) => da + em = g
-  let (g, d, _) = <(Integer, Integer, Integer)>::from(a.gcd_cofactors_ref(m));
+  let (g, d, _) = bezout(a, m);
 
   // q = floor_div(b, g)
   // r = b % g
@@ -81,7 +180,7 @@ where
     return Ok(acc);
   }
 
-  Ok(f(&acc, &divide_and_conquer_(&f, xs)?)?)
+  f(&acc, &divide_and_conquer_(&f, xs)?)
 }
 
 fn divide_and_conquer_<F, T: Clone, E>(f: &F, xs: &[T]) -> Result<T, E>
@@ -95,10 +194,10 @@ where
   let mid = xs.len() / 2;
   let left = &xs[..mid];
   let right = &xs[mid..];
-  Ok(f(
+  f(
     &divide_and_conquer_(f, left)?,
     &divide_and_conquer_(f, right)?,
-  )?)
+  )
 }
 
 #[cfg(test)]
@@ -116,11 +215,72 @@ mod tests {
     divide_and_conquer(
       |a, b| -> Result<Integer, Never> { Ok(int(a * b)) },
       int(1),
-      &xs,
+      xs,
     )
     .unwrap()
   }
 
+  #[test]
+  fn test_product_bits() {
+    let primes = ["a", "b", "c", "d"]
+      .iter()
+      .map(hash_to_prime)
+      .collect::<Vec<_>>();
+    for n in 1..=primes.len() {
+      let bound = product_bits(&primes[..n]);
+      let actual = u64::from(merge_product(&primes[..n]).significant_bits());
+      assert!(actual <= bound);
+      assert!(bound - actual < n as u64);
+    }
+    assert_eq!(product_bits(&[]), 0);
+  }
+
+  #[test]
+  fn test_random_bits() {
+    let x = random_bits(&mut test_rng(7), 1000);
+    assert_eq!(x, random_bits(&mut test_rng(7), 1000));
+    assert_ne!(x, random_bits(&mut test_rng(8), 1000));
+    assert!(x.significant_bits() <= 1000);
+    // Each extra call draws fresh bytes.
+    let mut rng = test_rng(7);
+    assert_ne!(random_bits(&mut rng, 1000), random_bits(&mut rng, 1000));
+    for bits in 0..20 {
+      assert!(random_bits(&mut rng, bits).significant_bits() <= bits);
+    }
+  }
+
+  #[test]
+  fn test_product_mod() {
+    let primes = ["a", "b", "c", "d"]
+      .iter()
+      .map(hash_to_prime)
+      .collect::<Vec<_>>();
+    let l = hash_to_prime("l");
+    assert_eq!(product_mod(&primes, &l), merge_product(&primes) % &l);
+    assert_eq!(product_mod(&primes, &int(1)), 0);
+    assert_eq!(product_mod(&[], &l), 1);
+    assert_eq!(product_mod(&[int(3), int(5)], &int(7)), 1);
+  }
+
+  #[test]
+  fn test_exp_product() {
+    let base = Rsa2048::unknown_order_elem();
+    let xs = [int(5), int(7), int(11)];
+    assert!(exp_product::<Rsa2048>(&base, &xs) == Rsa2048::exp(&base, &int(385)));
+    assert!(exp_product::<Rsa2048>(&base, &[]) == base);
+  }
+
+  #[test]
+  fn test_exp_signed_product() {
+    let base = Rsa2048::unknown_order_elem();
+    let expected = Rsa2048::inv(&Rsa2048::exp(&base, &int(15)));
+    assert!(exp_signed_product::<Rsa2048>(&base, &[int(3), int(-5)]) == expected);
+    assert!(
+      exp_signed_product::<Rsa2048>(&base, &[int(-3), int(-5)]) == Rsa2048::exp(&base, &int(15))
+    );
+    assert!(exp_signed_product::<Rsa2048>(&base, &[]) == base);
+  }
+
   #[test]
   fn test_linear_congruence_solver() {
     assert_eq!(
@@ -181,7 +341,36 @@ mod tests {
     let (x, y, z) = (&int(7), &int(14), &int(19)); // Inputs not coprime.
     let xth_root = Rsa2048::exp(&Rsa2048::unknown_order_elem(), &int(y * z));
     let yth_root = Rsa2048::exp(&Rsa2048::unknown_order_elem(), &int(x * z));
-    assert!(shamir_trick::<Rsa2048>(&xth_root, &yth_root, x, y) == None);
+    assert!(shamir_trick::<Rsa2048>(&xth_root, &yth_root, x, y).is_none());
+  }
+
+  #[test]
+  fn test_bezout() {
+    let check = |a: &Integer, b: &Integer| {
+      let (gcd, x, y) = bezout(a, b);
+      assert!(gcd >= 0);
+      assert!(gcd == int(a.gcd_ref(b)));
+      assert!(int(a * &x) + int(b * &y) == gcd);
+      gcd
+    };
+    let mut rng = test_rng(0);
+    let p = hash_to_prime(&"p");
+    let q = hash_to_prime(&"q");
+    for _ in 0..4 {
+      let a = random_bits(&mut rng, 4096);
+      let b = random_bits(&mut rng, 4096);
+      check(&a, &b);
+      // Coprime: a product of primes and one of distinct primes.
+      assert!(check(&int(&a * &p), &int(&b * &q).next_prime()) == 1);
+      // Not coprime: a shared 4096-bit factor.
+      let c = random_bits(&mut rng, 4096) | 1;
+      assert!(check(&int(&a * &c), &int(&b * &c)) % &c == 0);
+      check(&int(-&a), &b);
+      check(&a, &int(-&b));
+    }
+    assert!(check(&int(0), &int(0)) == 0);
+    assert!(check(&int(0), &int(-5)) == 5);
+    assert!(check(&int(12), &int(12)) == 12);
   }
 
   #[test]